@@ -16,11 +16,11 @@
 
 use std::cmp::Ordering;
 
-use anyhow::Context;
-
+use crate::e621::error::Result;
 use crate::e621::io::parser::BaseParser;
 use crate::e621::sender::RequestSender;
 use crate::e621::sender::entries::{PostEntry, UserEntry};
+use crate::e621::sender::runtime::block_on;
 
 /// Root token which contains all the tokens of the blacklist.
 #[derive(Default, Debug)]
@@ -110,7 +110,7 @@ impl BlacklistParser {
     }
 
     /// Parses the entire blacklist.
-    fn parse_blacklist(&mut self) -> RootToken {
+    fn parse_blacklist(&mut self) -> Result<RootToken> {
         trace!("Parsing blacklist...");
         let mut lines: Vec<LineToken> = Vec::new();
         loop {
@@ -119,16 +119,16 @@ impl BlacklistParser {
                 break;
             }
 
-            lines.push(self.parse_line());
+            lines.push(self.parse_line()?);
         }
 
         trace!("Parsed blacklist...");
 
-        RootToken { lines }
+        Ok(RootToken { lines })
     }
 
     /// Parses each tag and collects them into a [`LineToken`].
-    fn parse_line(&mut self) -> LineToken {
+    fn parse_line(&mut self) -> Result<LineToken> {
         let mut tags: Vec<TagToken> = Vec::new();
         loop {
             if self.base_parser.starts_with("\n") {
@@ -141,10 +141,10 @@ impl BlacklistParser {
                 break;
             }
 
-            tags.push(self.parse_tag());
+            tags.push(self.parse_tag()?);
         }
 
-        LineToken::new(tags)
+        Ok(LineToken::new(tags))
     }
 
     /// Checks if tag is negated.
@@ -153,7 +153,7 @@ impl BlacklistParser {
     }
 
     /// Parses tag and runs through basic identification before returning it as a [`TagToken`].
-    fn parse_tag(&mut self) -> TagToken {
+    fn parse_tag(&mut self) -> Result<TagToken> {
         let mut token = TagToken::default();
         if self.is_tag_negated() {
             assert_eq!(self.base_parser.consume_char(), '-');
@@ -164,10 +164,10 @@ impl BlacklistParser {
 
         // This will be considered a special tag if it contains the syntax of one.
         if !self.base_parser.eof() && self.base_parser.next_char() == ':' {
-            self.parse_special_tag(&mut token);
+            self.parse_special_tag(&mut token)?;
         }
 
-        token
+        Ok(token)
     }
 
     /// Parses special tag and updates token with the appropriate type and value.
@@ -176,13 +176,13 @@ impl BlacklistParser {
     ///
     /// * `token`: The special [`TagToken`] to parse.
     ///
-    /// returns: ()
+    /// returns: Result<(), E621Error>
     ///
     /// # Errors
     ///
-    /// An error can occur if 1) the `assert_eq` fails in its check or 2) if the [`TagToken`] name is not any of the matched
-    /// values.
-    fn parse_special_tag(&mut self, token: &mut TagToken) {
+    /// An error can occur if 1) the `assert_eq` fails in its check, 2) if the [`TagToken`] name is not any of the
+    /// matched values, or 3) if a score is not a number.
+    fn parse_special_tag(&mut self, token: &mut TagToken) -> Result<()> {
         assert_eq!(self.base_parser.consume_char(), ':');
         match token.name.as_str() {
             "rating" => {
@@ -203,14 +203,20 @@ impl BlacklistParser {
             "score" => {
                 let ordering = self.get_ordering();
                 let score = self.base_parser.consume_while(valid_score);
-                token.tag_type = TagType::Score(ordering, score.parse::<i32>().unwrap());
+                let score = score.parse::<i32>().map_err(|_| {
+                    self.base_parser
+                        .report_blacklist_error(format!("Invalid score: {score}").as_str())
+                })?;
+                token.tag_type = TagType::Score(ordering, score);
             }
             _ => {
-                self.base_parser.report_error(
+                return Err(self.base_parser.report_blacklist_error(
                     format!("Unknown special tag identifier: {}", token.name).as_str(),
-                );
+                ));
             }
         }
+
+        Ok(())
     }
 
     /// Checks the value and create a new [Rating] from it.
@@ -455,14 +461,10 @@ impl FlagWorker {
                     }
                 }
                 TagType::User(_) => {
-                    let user_id = tag
-                        .name
-                        .parse::<i64>()
-                        .with_context(|| {
-                            format!("Failed to parse blacklisted user id: {}!", tag.name)
-                        })
-                        .unwrap();
-                    self.flag_user(user_id, post.uploader_id, tag.negated);
+                    // The user id is only available if it was cached, a user that couldn't be found is skipped.
+                    if let Ok(user_id) = tag.name.parse::<i64>() {
+                        self.flag_user(user_id, post.uploader_id, tag.negated);
+                    }
                 }
                 TagType::Score(ordering, score) => {
                     self.flag_score(ordering, score, post.score.total, tag.negated);
@@ -518,6 +520,8 @@ pub(crate) struct Blacklist {
     blacklist_parser: BlacklistParser,
     /// All of the blacklist tokens after being parsed.
     blacklist_tokens: RootToken,
+    /// The blacklist as the user wrote it.
+    text: String,
     /// Request sender used for getting user information.
     request_sender: RequestSender,
 }
@@ -527,6 +531,7 @@ impl Blacklist {
         Blacklist {
             blacklist_parser: BlacklistParser::default(),
             blacklist_tokens: RootToken::default(),
+            text: String::new(),
             request_sender,
         }
     }
@@ -537,14 +542,17 @@ impl Blacklist {
     ///
     /// * `user_blacklist`: The user blacklist to parse
     ///
-    /// returns: &mut Blacklist
-    pub(crate) fn parse_blacklist(&mut self, user_blacklist: String) -> &mut Blacklist {
+    /// returns: Result<&mut Blacklist, E621Error>
+    pub(crate) fn parse_blacklist(&mut self, user_blacklist: String) -> Result<&mut Blacklist> {
+        self.text.clone_from(&user_blacklist);
         self.blacklist_parser = BlacklistParser::new(user_blacklist);
-        self.blacklist_tokens = self.blacklist_parser.parse_blacklist();
-        self
+        self.blacklist_tokens = self.blacklist_parser.parse_blacklist()?;
+        Ok(self)
     }
 
     /// Caches user id into the tag name for quicker access during the blacklist checks.
+    ///
+    /// A user that can't be looked up is reported and skipped, leaving the rest of the blacklist intact.
     pub(crate) fn cache_users(&mut self) {
         let tags: Vec<&mut TagToken> = self
             .blacklist_tokens
@@ -554,14 +562,28 @@ impl Blacklist {
             .collect();
         for tag in tags {
             if let TagType::User(Some(username)) = &tag.tag_type {
-                let user: UserEntry = self
-                    .request_sender
-                    .get_entry_from_appended_id(username, "user");
-                tag.name = format!("{}", user.id);
+                let user: Result<UserEntry> = block_on(
+                    self.request_sender
+                        .get_entry_from_appended_id(username, "user"),
+                );
+                match user {
+                    Ok(user) => tag.name = format!("{}", user.id),
+                    Err(error) => warn!(
+                        "Couldn't look up blacklisted user {}, skipping them: {error}",
+                        console::style(format!("\"{username}\""))
+                            .color256(39)
+                            .italic()
+                    ),
+                }
             }
         }
     }
 
+    /// The blacklist as the user wrote it.
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
     /// Checks if the blacklist is empty.
     pub(crate) fn is_empty(&self) -> bool {
         self.blacklist_tokens.lines.is_empty()
@@ -0,0 +1,234 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+/// A [Result] that fails with an [`E621Error`].
+pub(crate) type Result<T, E = E621Error> = std::result::Result<T, E>;
+
+/// Every error that can happen while grabbing and downloading posts.
+///
+/// These are returned instead of exiting the program, so a caller can decide whether to skip what failed and continue.
+#[derive(Debug)]
+pub(crate) enum E621Error {
+    /// A request couldn't be sent, or the connection dropped while reading the response.
+    Network {
+        /// The url of the request, if known.
+        url: Option<String>,
+        /// The underlying error.
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// The server answered with a status that isn't successful.
+    HttpStatus {
+        /// The url of the request.
+        url: String,
+        /// The status the server answered with.
+        status: StatusCode,
+    },
+    /// A response couldn't be deserialized into the expected type.
+    Deserialize {
+        /// What was being deserialized.
+        context: String,
+        /// The underlying error.
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// The tag file contains invalid syntax.
+    TagSyntax {
+        /// The character the error happened at.
+        position: usize,
+        /// The column the error happened at.
+        column: usize,
+        /// What is wrong with the syntax.
+        message: String,
+    },
+    /// The user's blacklist contains invalid syntax.
+    BlacklistSyntax {
+        /// The character the error happened at.
+        position: usize,
+        /// The column the error happened at.
+        column: usize,
+        /// What is wrong with the syntax.
+        message: String,
+    },
+    /// A tag in the tag file doesn't exist on the server.
+    UnknownTag(String),
+    /// A file or directory couldn't be read or written.
+    Filesystem {
+        /// The path of the file or directory.
+        path: PathBuf,
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// A download received no data for longer than the read-idle timeout.
+    Stalled {
+        /// The url of the download.
+        url: String,
+        /// How long no data was received for.
+        idle: Duration,
+    },
+    /// A download took longer than its overall timeout.
+    TimedOut {
+        /// The url of the download.
+        url: String,
+        /// How long the download was allowed to take.
+        after: Duration,
+    },
+    /// A downloaded file didn't match the md5 the server listed for it.
+    ChecksumMismatch {
+        /// The path the file was going to be saved to.
+        path: PathBuf,
+        /// The md5 the server listed for the file.
+        expected: String,
+        /// The md5 of the downloaded file.
+        actual: String,
+    },
+    /// The tag file didn't exist and an example one was created, which needs to be filled in before grabbing posts.
+    TagFileCreated(PathBuf),
+}
+
+impl E621Error {
+    /// Creates a [`E621Error::Deserialize`] error.
+    ///
+    /// # Arguments
+    ///
+    /// * `context`: What was being deserialized.
+    /// * `source`: The underlying error.
+    ///
+    /// returns: `E621Error`
+    pub(crate) fn deserialize(
+        context: impl Into<String>,
+        source: impl Into<Box<dyn Error + Send + Sync>>,
+    ) -> Self {
+        E621Error::Deserialize {
+            context: context.into(),
+            source: source.into(),
+        }
+    }
+
+    /// Creates a [`E621Error::Filesystem`] error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the file or directory.
+    /// * `source`: The underlying error.
+    ///
+    /// returns: `E621Error`
+    pub(crate) fn filesystem(path: &Path, source: io::Error) -> Self {
+        E621Error::Filesystem {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+impl Display for E621Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            E621Error::Network {
+                url: Some(url),
+                source,
+            } => write!(f, "Request to {url} failed: {source}"),
+            E621Error::Network { url: None, source } => write!(f, "Request failed: {source}"),
+            E621Error::HttpStatus { url, status } => {
+                write!(f, "Server responded to {url} with status {status}")
+            }
+            E621Error::Deserialize { context, source } => {
+                write!(f, "Unable to deserialize {context}: {source}")
+            }
+            E621Error::TagSyntax {
+                position,
+                column,
+                message,
+            } => write!(
+                f,
+                "Error parsing file at character {position} (column {column}): {message}"
+            ),
+            E621Error::BlacklistSyntax {
+                position,
+                column,
+                message,
+            } => write!(
+                f,
+                "Error parsing blacklist at character {position} (column {column}): {message}"
+            ),
+            E621Error::UnknownTag(tag) => {
+                write!(f, "The server API call was unable to find tag: {tag}!")
+            }
+            E621Error::Filesystem { path, source } => {
+                write!(f, "Unable to access \"{}\": {source}", path.display())
+            }
+            E621Error::Stalled { url, idle } => write!(
+                f,
+                "Download of {url} stalled, no data was received for {}s",
+                idle.as_secs()
+            ),
+            E621Error::TimedOut { url, after } => write!(
+                f,
+                "Download of {url} took longer than its timeout of {}s",
+                after.as_secs()
+            ),
+            E621Error::ChecksumMismatch {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Downloaded \"{}\" has md5 {actual}, but {expected} was expected",
+                path.display()
+            ),
+            E621Error::TagFileCreated(path) => write!(
+                f,
+                "The tag file \"{}\" was created, include the artists, sets, pools, and individual posts \
+                 you wish to download in it and run the application again",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for E621Error {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            E621Error::Network { source, .. } | E621Error::Deserialize { source, .. } => {
+                Some(source.as_ref())
+            }
+            E621Error::Filesystem { source, .. } => Some(source),
+            E621Error::HttpStatus { .. }
+            | E621Error::TagSyntax { .. }
+            | E621Error::BlacklistSyntax { .. }
+            | E621Error::UnknownTag(_)
+            | E621Error::Stalled { .. }
+            | E621Error::TimedOut { .. }
+            | E621Error::ChecksumMismatch { .. }
+            | E621Error::TagFileCreated(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for E621Error {
+    fn from(source: reqwest::Error) -> Self {
+        E621Error::Network {
+            url: source.url().map(ToString::to_string),
+            source: source.into(),
+        }
+    }
+}
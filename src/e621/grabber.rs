@@ -14,15 +14,23 @@
  * limitations under the License.
  */
 
-use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
+use std::mem::take;
+use std::path::Path;
+use std::slice;
+use std::sync::{Arc, Mutex, RwLock};
+
+use tokio::sync::mpsc::UnboundedSender;
 
 use crate::e621::blacklist::Blacklist;
+use crate::e621::error::Result;
+use crate::e621::io::state_file::stable_hash;
+use crate::e621::io::sync_state::SyncState;
 use crate::e621::io::tag::{Group, Tag, TagSearchType, TagType};
-use crate::e621::io::{Config, Login};
-use crate::e621::sender::RequestSender;
-use crate::e621::sender::entries::{PoolEntry, PostEntry, SetEntry};
+use crate::e621::io::{Config, FileVariant, Login, PostLimit, RatingFilter};
+use crate::e621::sender::entries::{PoolEntry, PostEntry, Relationships, SetEntry};
+use crate::e621::sender::{RequestSender, SearchPage};
 
 /// A trait for implementing a conversion function for turning a type into a [Vec] of the same type
 ///
@@ -34,13 +42,20 @@ pub(crate) trait NewVec<T> {
 }
 
 /// A collection of values taken from a [`PostEntry`].
+#[derive(Clone)]
 pub(crate) struct GrabbedPost {
     /// The url that leads to the file to download.
     url: String,
     /// The name of the file to download.
     name: String,
-    /// The size of the file to download.
-    file_size: i64,
+    /// The size of the file to download, if the server lists it.
+    file_size: Option<i64>,
+    /// The md5 of the file to download, if the server lists it.
+    md5: Option<String>,
+    /// The md5 of the post's original file, which identifies the post even when a sample or preview is downloaded.
+    post_md5: String,
+    /// The variant of the post's file that is downloaded.
+    variant: FileVariant,
 }
 
 impl GrabbedPost {
@@ -54,38 +69,163 @@ impl GrabbedPost {
         &self.name
     }
 
-    /// The size of the file to download.
-    pub(crate) fn file_size(&self) -> i64 {
+    /// The size of the file to download, if the server lists it.
+    ///
+    /// Only the original file has a listed size, samples and previews don't.
+    pub(crate) fn file_size(&self) -> Option<i64> {
         self.file_size
     }
+
+    /// The md5 of the file to download, if the server lists it.
+    ///
+    /// Only the original file has a listed md5, samples and previews don't.
+    pub(crate) fn md5(&self) -> Option<&str> {
+        self.md5.as_deref()
+    }
+
+    /// The md5 of the post's original file along with the variant that is downloaded, which identifies the downloaded
+    /// file across collections.
+    ///
+    /// returns: Option<(&str, `FileVariant`)>, which is [None] if the server doesn't list an md5 for the post.
+    pub(crate) fn file_key(&self) -> Option<(&str, FileVariant)> {
+        (!self.post_md5.is_empty()).then_some((self.post_md5.as_str(), self.variant))
+    }
+
+    /// Creates a [`GrabbedPost`] that downloads the given file under the given name.
+    ///
+    /// Samples and previews have the variant appended to their name (e.g `123_sample.jpg`), so they don't overwrite
+    /// the original file of the same post when it is downloaded too.
+    ///
+    /// # Arguments
+    ///
+    /// * `file`: The file to download.
+    /// * `name`: The name of the file, without the variant suffix and extension.
+    ///
+    /// returns: `GrabbedPost`
+    fn new(file: VariantFile, name: &str) -> Self {
+        GrabbedPost {
+            url: file.url,
+            name: format!("{name}{}.{}", file.variant.suffix(), file.ext),
+            file_size: file.size,
+            md5: file.md5,
+            post_md5: file.post_md5,
+            variant: file.variant,
+        }
+    }
+
+    /// Creates a [`GrabbedPost`] for a post related to a grabbed one, which is named after its relation to it (e.g
+    /// `123_parent` or `123_child_2`) so it's saved next to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The related post.
+    /// * `relation`: The name of the relation, without the extension.
+    /// * `variant`: The variant of its file to download.
+    ///
+    /// returns: `GrabbedPost`
+    fn related(post: &PostEntry, relation: &str, variant: FileVariant) -> Self {
+        GrabbedPost::new(VariantFile::new(post, variant), relation)
+    }
+}
+
+/// The url and details of the variant of a post's file that is downloaded.
+struct VariantFile {
+    /// The url that leads to the file.
+    url: String,
+    /// The extension of the file.
+    ext: String,
+    /// The size of the file, if the server lists it.
+    size: Option<i64>,
+    /// The md5 of the file, if the server lists it.
+    md5: Option<String>,
+    /// The md5 of the post's original file.
+    post_md5: String,
+    /// The variant that is downloaded, which is [`FileVariant::Original`] if the post doesn't have the one asked for.
+    variant: FileVariant,
+}
+
+impl VariantFile {
+    /// Picks the file of the post to download for the given variant.
+    ///
+    /// A post without a sample falls back to its original file. Posts without a preview are filtered out when only
+    /// previews are grabbed, so one reaching here also falls back, but with a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to pick the file of.
+    /// * `variant`: The variant to download.
+    ///
+    /// returns: `VariantFile`
+    fn new(post: &PostEntry, variant: FileVariant) -> Self {
+        let url = match variant {
+            FileVariant::Original => None,
+            FileVariant::Sample => post
+                .sample
+                .url
+                .as_ref()
+                .filter(|_| post.sample.has == Some(true)),
+            FileVariant::Preview => post.preview.url.as_ref(),
+        };
+        if url.is_none() && variant == FileVariant::Preview {
+            warn!(
+                "Post {} has no preview, downloading its original file instead...",
+                console::style(format!("\"{}\"", post.id))
+                    .color256(39)
+                    .italic()
+            );
+        }
+
+        match url {
+            Some(url) => VariantFile {
+                url: url.clone(),
+                ext: Path::new(url).extension().map_or_else(
+                    || post.file.ext.clone(),
+                    |e| e.to_string_lossy().to_string(),
+                ),
+                size: None,
+                md5: None,
+                post_md5: post.file.md5.clone(),
+                variant,
+            },
+            None => VariantFile {
+                url: post.file.url.clone().expect("Post URL is missing!"),
+                ext: post.file.ext.clone(),
+                size: Some(post.file.size),
+                md5: Some(post.file.md5.clone()),
+                post_md5: post.file.md5.clone(),
+                variant: FileVariant::Original,
+            },
+        }
+    }
 }
 
-impl NewVec<Vec<PostEntry>> for GrabbedPost {
-    /// Creates a new [Vec] of type [`GrabbedPost`] from Vec of type [`PostEntry`]
+impl NewVec<(Vec<PostEntry>, FileVariant)> for GrabbedPost {
+    /// Creates a new [Vec] of type [`GrabbedPost`] from tuple contains types ([`PostEntry`], [`FileVariant`])
     ///
     /// # Arguments
     ///
-    /// * `vec`: The vector to be consumed and converted.
+    /// * `(vec, variant)`: A tuple containing the posts and the variant of their files to download.
     ///
     /// returns: Vec<`GrabbedPost`, Global>
-    fn new_vec(vec: Vec<PostEntry>) -> Vec<Self> {
+    fn new_vec((vec, variant): (Vec<PostEntry>, FileVariant)) -> Vec<Self> {
         vec.into_iter()
-            .map(|e| GrabbedPost::from((e, Config::get().naming_convention())))
+            .map(|e| GrabbedPost::from((e, Config::get().naming_convention(), variant)))
             .collect()
     }
 }
 
-impl NewVec<(Vec<PostEntry>, &str)> for GrabbedPost {
-    /// Creates a new [Vec] of type [`GrabbedPost`] from tuple contains types ([`PostEntry`], &str)
+impl NewVec<(Vec<PostEntry>, &str, FileVariant)> for GrabbedPost {
+    /// Creates a new [Vec] of type [`GrabbedPost`] from tuple contains types ([`PostEntry`], &str, [`FileVariant`])
     ///
     /// Compared to the other overload, this version sets the name of the [`GrabbedPost`] and numbers them.
     ///
     /// # Arguments
     ///
-    /// * `(vec, pool_name)`: A tuple containing the posts and the name of the pool associated with them.
+    /// * `(vec, pool_name, variant)`: A tuple containing the posts, the name of the pool associated with them, and
+    ///   the variant of their files to download.
     ///
     /// returns: Vec<`GrabbedPost`, Global>
-    fn new_vec((vec, pool_name): (Vec<PostEntry>, &str)) -> Vec<Self> {
+    fn new_vec((vec, pool_name, variant): (Vec<PostEntry>, &str, FileVariant)) -> Vec<Self> {
         vec.iter()
             .enumerate()
             .map(|(i, e)| {
@@ -93,42 +233,42 @@ impl NewVec<(Vec<PostEntry>, &str)> for GrabbedPost {
                     e,
                     pool_name,
                     u16::try_from(i + 1).expect("Something went wrong."),
+                    variant,
                 ))
             })
             .collect()
     }
 }
 
-impl From<(&PostEntry, &str, u16)> for GrabbedPost {
-    /// Creates [`GrabbedPost`] from tuple of types (&[`PostEntry`], &str, u16)
+impl From<(&PostEntry, &str, u16, FileVariant)> for GrabbedPost {
+    /// Creates [`GrabbedPost`] from tuple of types (&[`PostEntry`], &str, u16, [`FileVariant`])
     ///
     /// # Arguments
     ///
-    /// * `(post, name, current_page)`: A tuple containing the post, name, and current page number of post.
+    /// * `(post, name, current_page, variant)`: A tuple containing the post, name, current page number of post, and
+    ///   the variant of its file to download.
     ///
     /// returns: `GrabbedPost`
-    fn from((post, name, current_page): (&PostEntry, &str, u16)) -> Self {
-        GrabbedPost {
-            url: post.file.url.clone().expect("Post URL is missing!"),
-            name: format!("{} Page_{:05}.{}", name, current_page, post.file.ext),
-            file_size: post.file.size,
-        }
+    fn from((post, name, current_page, variant): (&PostEntry, &str, u16, FileVariant)) -> Self {
+        let name = format!("{name} Page_{current_page:05}");
+        GrabbedPost::new(VariantFile::new(post, variant), &name)
     }
 }
 
-impl From<(PostEntry, &str)> for GrabbedPost {
-    /// Creates [`GrabbedPost`] from tuple of types ([`PostEntry`], &str)
+impl From<(PostEntry, &str, FileVariant)> for GrabbedPost {
+    /// Creates [`GrabbedPost`] from tuple of types ([`PostEntry`], &str, [`FileVariant`])
     ///
     /// # Arguments
     ///
-    /// * `(post, name_convention)`: A tuple containing the post, and naming convention of post.
+    /// * `(post, name_convention, variant)`: A tuple containing the post, naming convention of post, and the variant
+    ///   of its file to download.
     ///
     /// returns: `GrabbedPost`
-    fn from((post, name_convention): (PostEntry, &str)) -> Self {
+    fn from((post, name_convention, variant): (PostEntry, &str, FileVariant)) -> Self {
         let name = if name_convention.eq_ignore_ascii_case("md5") {
-            format!("{}.{}", post.file.md5, post.file.ext)
+            post.file.md5.clone()
         } else if name_convention.eq_ignore_ascii_case("id") {
-            format!("{}.{}", post.id, post.file.ext)
+            post.id.to_string()
         } else {
             let mut name = name_convention.to_string();
             name = name.replace("{id}", &post.id.to_string());
@@ -140,16 +280,10 @@ impl From<(PostEntry, &str)> for GrabbedPost {
             name = name.replace("{general}", &post.tags.general.join(" "));
             name = name.replace("{meta}", &post.tags.meta.join(" "));
             name = name.replace("{lore}", &post.tags.lore.join(" "));
-            name = name.replace("{rating}", &post.rating);
-
-            format!("{}.{}", name, post.file.ext)
+            name.replace("{rating}", &post.rating)
         };
 
-        GrabbedPost {
-            url: post.file.url.clone().expect("Post URL is missing!"),
-            name,
-            file_size: post.file.size,
-        }
+        GrabbedPost::new(VariantFile::new(&post, variant), &name)
     }
 }
 
@@ -167,6 +301,9 @@ pub(crate) struct PostCollection {
     category: String,
     /// The posts in the set.
     posts: Vec<GrabbedPost>,
+    /// The search the posts came from and the newest post ID in it, which is marked in the sync state once every post
+    /// is downloaded.
+    sync_mark: Option<(String, i64)>,
 }
 
 impl PostCollection {
@@ -184,9 +321,26 @@ impl PostCollection {
             name: name.to_string(),
             category: category.to_string(),
             posts,
+            sync_mark: None,
         }
     }
 
+    /// Sets the search the posts came from and the newest post ID in it, to mark in the sync state once every post is
+    /// downloaded.
+    ///
+    /// Nothing is marked if there is no newest post, which happens when the search found nothing new.
+    ///
+    /// # Arguments
+    ///
+    /// * `search`: The search the posts came from.
+    /// * `newest_id`: The newest post ID in the search.
+    ///
+    /// returns: `PostCollection`
+    pub(crate) fn with_sync_mark(mut self, search: &str, newest_id: Option<i64>) -> Self {
+        self.sync_mark = newest_id.map(|id| (search.to_string(), id));
+        self
+    }
+
     /// The name of the set.
     pub(crate) fn name(&self) -> &str {
         &self.name
@@ -201,6 +355,11 @@ impl PostCollection {
     pub(crate) fn posts(&self) -> &Vec<GrabbedPost> {
         &self.posts
     }
+
+    /// The search the posts came from and the newest post ID in it, if it is tracked in the sync state.
+    pub(crate) fn sync_mark(&self) -> Option<&(String, i64)> {
+        self.sync_mark.as_ref()
+    }
 }
 
 impl Shorten<&str> for PostCollection {
@@ -254,19 +413,81 @@ impl From<(&SetEntry, Vec<GrabbedPost>)> for PostCollection {
     }
 }
 
-/// The total amount of pages the general search can search for.
-const POST_SEARCH_LIMIT: u8 = 5;
+/// The last page the API allows when searching by page number.
+const MAX_NUMBERED_PAGE: u16 = 750;
+
+/// The maximum amount of related posts looked up in a single API call.
+const RELATED_BATCH_SIZE: usize = 100;
+
+/// The amount of posts removed from a search, by the reason they were removed.
+#[derive(Default)]
+struct FilteredPosts {
+    /// Posts with a rating that isn't grabbed.
+    rating: u16,
+    /// Posts removed by the blacklist.
+    blacklisted: u16,
+    /// Posts without a valid file.
+    invalid: u16,
+    /// Posts without a preview, when only previews are grabbed.
+    no_preview: u16,
+}
+
+impl FilteredPosts {
+    /// Logs how many posts were removed from the search for each reason.
+    fn log(&self) {
+        if self.rating > 0 {
+            info!(
+                "Filtered {} total posts by rating from search...",
+                console::style(self.rating).cyan().italic()
+            );
+        }
+
+        if self.blacklisted > 0 {
+            info!(
+                "Filtered {} total blacklisted posts from search...",
+                console::style(self.blacklisted).cyan().italic()
+            );
+        }
+
+        if self.invalid > 0 {
+            info!(
+                "Filtered {} total invalid posts from search...",
+                console::style(self.invalid).cyan().italic()
+            );
+        }
+
+        if self.no_preview > 0 {
+            info!(
+                "Filtered {} total posts without a preview from search...",
+                console::style(self.no_preview).cyan().italic()
+            );
+        }
+    }
+}
 
 /// Is a collector that grabs posts, categorizes them, and prepares them for the downloader to use in downloading.
+///
+/// Every collection is sent to the downloader as soon as it is grabbed, so downloading can start while the rest of the
+/// tags are still being grabbed.
 pub(crate) struct Grabber {
-    /// All grabbed posts.
-    posts: Vec<PostCollection>,
+    /// Posts grabbed by their ID, which are sent as a single collection once every tag is grabbed.
+    single_posts: Vec<GrabbedPost>,
+    /// Where grabbed collections are sent to be downloaded, while grabbing.
+    collections: Option<UnboundedSender<PostCollection>>,
     /// `RequestSender` for sending API calls.
     request_sender: RequestSender,
     /// Blacklist used to throwaway posts that contain tags the user may not want.
-    blacklist: Option<Rc<RefCell<Blacklist>>>,
-    /// Is grabber in safe mode or not
-    safe_mode: bool,
+    blacklist: Option<Arc<RwLock<Blacklist>>>,
+    /// Which variant of a post's file is downloaded for the group being grabbed.
+    file_variant: FileVariant,
+    /// How many posts are grabbed from each tag of the group being grabbed, if the group sets it.
+    group_limit: Option<PostLimit>,
+    /// How many steps of parents and children are grabbed along with each post of the group being grabbed.
+    relationship_depth: u8,
+    /// Which ratings of posts are grabbed for the group being grabbed.
+    ratings: RatingFilter,
+    /// The newest post downloaded from each search in earlier runs, if incremental sync is enabled.
+    sync_state: Option<Arc<Mutex<SyncState>>>,
 }
 
 impl Grabber {
@@ -275,48 +496,142 @@ impl Grabber {
     /// # Arguments
     ///
     /// * `request_sender`: The client to perform the searches.
-    /// * `safe_mode`: Which mode the grabber will operate under.
     ///
     /// returns: Grabber
-    pub(crate) fn new(request_sender: RequestSender, safe_mode: bool) -> Self {
+    pub(crate) fn new(request_sender: RequestSender) -> Self {
         Grabber {
-            posts: vec![PostCollection::new("Single Posts", "", Vec::new())],
+            single_posts: Vec::new(),
+            collections: None,
             request_sender,
             blacklist: None,
-            safe_mode,
+            file_variant: Config::get().file_variant(),
+            group_limit: None,
+            relationship_depth: Config::get().relationship_depth(),
+            ratings: Config::get().ratings(),
+            sync_state: None,
         }
     }
 
-    /// All grabbed posts.
-    pub(crate) fn posts(&self) -> &Vec<PostCollection> {
-        &self.posts
-    }
-
     /// Sets the blacklist.
     ///
     /// # Arguments
     ///
     /// * `blacklist`: The new blacklist
-    pub(crate) fn set_blacklist(&mut self, blacklist: Rc<RefCell<Blacklist>>) {
-        if !blacklist.borrow_mut().is_empty() {
+    pub(crate) fn set_blacklist(&mut self, blacklist: Arc<RwLock<Blacklist>>) {
+        if !blacklist
+            .read()
+            .expect("Blacklist lock was poisoned!")
+            .is_empty()
+        {
             self.blacklist = Some(blacklist);
         }
     }
 
-    /// Sets safe mode.
+    /// Sets the sync state, which makes searches stop at the newest post downloaded from them in an earlier run.
+    ///
+    /// # Arguments
+    ///
+    /// * `sync_state`: The sync state loaded for this run.
+    pub(crate) fn set_sync_state(&mut self, sync_state: Arc<Mutex<SyncState>>) {
+        self.sync_state = Some(sync_state);
+    }
+
+    /// Gets the newest post downloaded from the search in an earlier run, if incremental sync is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `search`: The search to look up.
+    ///
+    /// returns: Option<i64>
+    fn last_seen(&self, search: &str) -> Option<i64> {
+        self.sync_state
+            .as_ref()?
+            .lock()
+            .expect("Sync state lock was poisoned!")
+            .last_seen(search)
+    }
+
+    /// Gets the key a search is saved under in the sync state.
+    ///
+    /// The key includes a hash of the filters the search is grabbed with (the ratings, the blacklist and the limit), so
+    /// changing any of them walks the search in full again instead of skipping older posts they now allow.
+    ///
+    /// # Arguments
+    ///
+    /// * `search`: The search to get the key of.
+    /// * `limit`: How many posts are grabbed from the search.
+    ///
+    /// returns: String
+    fn sync_key(&self, search: &str, limit: PostLimit) -> String {
+        let blacklist = self
+            .blacklist
+            .as_ref()
+            .map(|e| {
+                e.read()
+                    .expect("Blacklist lock was poisoned!")
+                    .text()
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let filters = format!("{}\n{limit}\n{blacklist}", self.ratings);
+        format!("{search} [{}]", stable_hash(&filters))
+    }
+
+    /// Gets how many posts are grabbed from the tag, which is set by the tag's line, then by its group, then by the
+    /// config depending on the kind of search.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag`: The tag to get the limit of.
+    ///
+    /// returns: `PostLimit`
+    fn post_limit(&self, tag: &Tag) -> PostLimit {
+        tag.limit()
+            .or(self.group_limit)
+            .unwrap_or_else(|| Config::get().search_limits().for_search(tag.search_type()))
+    }
+
+    /// Grabs the user's favorites and every tag, sending each collection to be downloaded as soon as it is grabbed.
+    ///
+    /// The channel is closed once everything is grabbed, which lets the downloader know no more collections are coming.
+    ///
+    /// # Arguments
     ///
-    /// If set true, the grabber will go into safe mode and grab only safe posts,
-    /// false will grab questionable and explicit posts.
+    /// * `groups`: The group of tags to search for.
+    /// * `collections`: Where grabbed collections are sent to be downloaded.
+    pub(crate) async fn grab_all(
+        &mut self,
+        groups: &[Group],
+        collections: UnboundedSender<PostCollection>,
+    ) {
+        trace!("Grabbing posts...");
+        self.collections = Some(collections);
+        if let Err(error) = self.grab_favorites().await {
+            error!("Skipping favorites as they could not be grabbed: {error}");
+        }
+
+        self.grab_posts_by_tags(groups).await;
+
+        let single_posts = take(&mut self.single_posts);
+        self.push_collection(PostCollection::new("Single Posts", "", single_posts));
+        self.collections = None;
+    }
+
+    /// Sends a grabbed collection to be downloaded.
     ///
     /// # Arguments
     ///
-    /// * `mode`: Which mode to run in
-    pub(crate) fn set_safe_mode(&mut self, mode: bool) {
-        self.safe_mode = mode;
+    /// * `collection`: The collection that was grabbed.
+    fn push_collection(&self, collection: PostCollection) {
+        if let Some(collections) = &self.collections
+            && collections.send(collection).is_err()
+        {
+            warn!("Downloads have stopped, the grabbed posts will not be downloaded...");
+        }
     }
 
     /// Grabs favorites from the user's favorites
-    pub(crate) fn grab_favorites(&mut self) {
+    async fn grab_favorites(&mut self) -> Result<()> {
         let login = Login::get();
         if !login.username().is_empty() && login.download_favorites() {
             let tag = format!("fav:{}", login.username());
@@ -328,39 +643,69 @@ impl Grabber {
                 None
             };
 
-            let posts = self.search(&tag, &TagSearchType::Special);
+            // Favorites are sorted by post ID, so an older post that was favorited recently would be skipped by the
+            // sync state. They are always walked in full instead.
+            let limit = Config::get()
+                .search_limits()
+                .for_search(&TagSearchType::Special);
+            // Related posts are grabbed before the blacklist is restored, so they bypass it along with the favorites.
+            let grabbed = match self
+                .search(&tag, &TagSearchType::Special, None, limit)
+                .await
+            {
+                Ok(posts) => {
+                    let related = self.grab_related(&posts).await;
+                    Ok((posts, related))
+                }
+                Err(error) => Err(error),
+            };
 
             if ignore_blacklist {
                 self.blacklist = original_blacklist;
             }
 
-            self.posts
-                .push(PostCollection::new(&tag, "", GrabbedPost::new_vec(posts)));
+            let (posts, related) = grabbed?;
+            let mut grabbed = GrabbedPost::new_vec((posts, self.file_variant));
+            grabbed.extend(related);
+            self.push_collection(PostCollection::new(&tag, "", grabbed));
             info!(
                 "{} grabbed!",
                 console::style(format!("\"{tag}\"")).color256(39).italic()
             );
         }
+
+        Ok(())
     }
 
     /// Grabs new posts by the given tag.
     ///
+    /// If grabbing a tag fails, the error is logged and the tag is skipped so the rest can still be grabbed.
+    ///
     /// # Arguments
     ///
     /// * `groups`: The group of tags to search for.
-    pub(crate) fn grab_posts_by_tags(&mut self, groups: &[Group]) {
-        let tags: Vec<&Tag> = groups
-            .iter()
-            .flat_map(super::io::tag::Group::tags)
-            .collect();
-        for tag in tags {
-            self.grab_by_tag_type(tag);
+    async fn grab_posts_by_tags(&mut self, groups: &[Group]) {
+        for group in groups {
+            self.file_variant = group.options().variant();
+            self.group_limit = group.options().limit();
+            self.relationship_depth = group.options().relationship_depth();
+            self.ratings = group.options().ratings();
+            for tag in group.tags() {
+                if let Err(error) = self.grab_by_tag_type(tag).await {
+                    error!(
+                        "Skipping {} as it could not be grabbed: {error}",
+                        console::style(format!("\"{}\"", tag.name()))
+                            .color256(39)
+                            .italic()
+                    );
+                }
+            }
         }
-    }
 
-    /// Returns the single post [`PostCollection`].
-    fn single_post_collection(&mut self) -> &mut PostCollection {
-        self.posts.first_mut().unwrap() // It is guaranteed that the first collection is the single post collection.
+        self.file_variant = Config::get().file_variant();
+        self.group_limit = None;
+        self.relationship_depth = Config::get().relationship_depth();
+        self.ratings = Config::get().ratings();
     }
 
     /// Adds a single post to the single post [`PostCollection`].
@@ -373,15 +718,18 @@ impl Grabber {
     /// # Warning
     ///
     /// This function will not add the single post provided if it has no direct valid URL.
-    fn add_single_post(&mut self, entry: PostEntry, id: i64) {
+    async fn add_single_post(&mut self, entry: PostEntry, id: i64) {
         if entry.file.url.is_none() {
             warn!(
                 "Post with ID {} has no URL!",
                 console::style(format!("\"{id}\"")).color256(39).italic()
             );
         } else {
-            let grabbed_post = GrabbedPost::from((entry, Config::get().naming_convention()));
-            self.single_post_collection().posts.push(grabbed_post);
+            let related = self.grab_related(slice::from_ref(&entry)).await;
+            let grabbed_post =
+                GrabbedPost::from((entry, Config::get().naming_convention(), self.file_variant));
+            self.single_posts.push(grabbed_post);
+            self.single_posts.extend(related);
             info!(
                 "Post with ID {} grabbed!",
                 console::style(format!("\"{id}\"")).color256(39).italic()
@@ -394,12 +742,12 @@ impl Grabber {
     /// # Arguments
     ///
     /// * `tag`: The tag to search for.
-    fn grab_by_tag_type(&mut self, tag: &Tag) {
+    async fn grab_by_tag_type(&mut self, tag: &Tag) -> Result<()> {
         match tag.tag_type() {
-            TagType::Pool => self.grab_pool(tag),
-            TagType::Set => self.grab_set(tag),
-            TagType::Post => self.grab_post(tag),
-            TagType::General | TagType::Artist => self.grab_general(tag),
+            TagType::Pool => self.grab_pool(tag).await,
+            TagType::Set => self.grab_set(tag).await,
+            TagType::Post => self.grab_post(tag).await,
+            TagType::General | TagType::Artist => self.grab_general(tag).await,
             TagType::Unknown => unreachable!(),
         }
     }
@@ -409,19 +757,35 @@ impl Grabber {
     /// # Arguments
     ///
     /// * `tag`: The tag to search for.
-    fn grab_general(&mut self, tag: &Tag) {
-        let posts = self.get_posts_from_tag(tag);
-        self.posts.push(PostCollection::new(
-            tag.name(),
-            "General Searches",
-            GrabbedPost::new_vec(posts),
-        ));
+    async fn grab_general(&mut self, tag: &Tag) -> Result<()> {
+        let limit = self.post_limit(tag);
+        let sync_key = self.sync_key(tag.name(), limit);
+        let since = match tag.search_type() {
+            TagSearchType::Special => self.last_seen(&sync_key),
+            _ => None,
+        };
+        let posts = self
+            .search(tag.name(), tag.search_type(), since, limit)
+            .await?;
+        let newest_id = match tag.search_type() {
+            TagSearchType::Special => posts.iter().map(|e| e.id).max(),
+            _ => None,
+        };
+        let related = self.grab_related(&posts).await;
+        let mut grabbed = GrabbedPost::new_vec((posts, self.file_variant));
+        grabbed.extend(related);
+        self.push_collection(
+            PostCollection::new(tag.name(), "General Searches", grabbed)
+                .with_sync_mark(&sync_key, newest_id),
+        );
         info!(
             "{} grabbed!",
             console::style(format!("\"{}\"", tag.name()))
                 .color256(39)
                 .italic()
         );
+
+        Ok(())
     }
 
     /// Grabs single post based on the given tag.
@@ -429,27 +793,28 @@ impl Grabber {
     /// # Arguments
     ///
     /// * `tag`: The tag to search for.
-    fn grab_post(&mut self, tag: &Tag) {
+    async fn grab_post(&mut self, tag: &Tag) -> Result<()> {
         let entry: PostEntry = self
             .request_sender
-            .get_entry_from_appended_id(tag.name(), "single");
+            .get_entry_from_appended_id(tag.name(), "single")
+            .await?;
         let id = entry.id;
 
-        if self.safe_mode {
-            match entry.rating.as_str() {
-                "s" => {
-                    self.add_single_post(entry, id);
-                }
-                _ => {
-                    info!(
-                        "Skipping Post: {} due to being explicit or questionable",
-                        console::style(format!("\"{id}\"")).color256(39).italic()
-                    );
-                }
-            }
+        if !self.ratings.allows(&entry.rating) {
+            info!(
+                "Skipping Post: {} due to its rating not being grabbed",
+                console::style(format!("\"{id}\"")).color256(39).italic()
+            );
+        } else if self.file_variant == FileVariant::Preview && entry.preview.url.is_none() {
+            info!(
+                "Skipping Post: {} due to it having no preview",
+                console::style(format!("\"{id}\"")).color256(39).italic()
+            );
         } else {
-            self.add_single_post(entry, id);
+            self.add_single_post(entry, id).await;
         }
+
+        Ok(())
     }
 
     /// Grabs a set based on the given tag.
@@ -457,15 +822,27 @@ impl Grabber {
     /// # Arguments
     ///
     /// * `tag`: The tag to search for.
-    fn grab_set(&mut self, tag: &Tag) {
+    async fn grab_set(&mut self, tag: &Tag) -> Result<()> {
         let entry: SetEntry = self
             .request_sender
-            .get_entry_from_appended_id(tag.name(), "set");
+            .get_entry_from_appended_id(tag.name(), "set")
+            .await?;
 
         // Grabs posts from IDs in the set entry.
-        let posts = self.search(&format!("set:{}", entry.shortname), &TagSearchType::Special);
-        self.posts
-            .push(PostCollection::from((&entry, GrabbedPost::new_vec(posts))));
+        let search = format!("set:{}", entry.shortname);
+        let limit = self.post_limit(tag);
+        let sync_key = self.sync_key(&search, limit);
+        let since = self.last_seen(&sync_key);
+        let posts = self
+            .search(&search, &TagSearchType::Special, since, limit)
+            .await?;
+        let newest_id = posts.iter().map(|e| e.id).max();
+        let related = self.grab_related(&posts).await;
+        let mut grabbed = GrabbedPost::new_vec((posts, self.file_variant));
+        grabbed.extend(related);
+        self.push_collection(
+            PostCollection::from((&entry, grabbed)).with_sync_mark(&sync_key, newest_id),
+        );
 
         info!(
             "{} grabbed!",
@@ -473,6 +850,8 @@ impl Grabber {
                 .color256(39)
                 .italic()
         );
+
+        Ok(())
     }
 
     /// Grabs pool based on the given tag.
@@ -480,12 +859,20 @@ impl Grabber {
     /// # Arguments
     ///
     /// * `tag`: The tag to search for.
-    fn grab_pool(&mut self, tag: &Tag) {
+    async fn grab_pool(&mut self, tag: &Tag) -> Result<()> {
         let mut entry: PoolEntry = self
             .request_sender
-            .get_entry_from_appended_id(tag.name(), "pool");
+            .get_entry_from_appended_id(tag.name(), "pool")
+            .await?;
         let name = &entry.name;
-        let mut posts = self.search(&format!("pool:{}", entry.id), &TagSearchType::Special);
+        let mut posts = self
+            .search(
+                &format!("pool:{}", entry.id),
+                &TagSearchType::Special,
+                None,
+                self.post_limit(tag),
+            )
+            .await?;
 
         // Updates entry post ids in case any posts were filtered in the search.
         entry
@@ -495,16 +882,114 @@ impl Grabber {
         // Sorts the pool to the original order given by entry.
         Self::sort_pool_by_id(&entry, &mut posts);
 
-        self.posts.push(PostCollection::new(
-            name,
-            "Pools",
-            GrabbedPost::new_vec((posts, name.as_ref())),
-        ));
+        let related = self.grab_related(&posts).await;
+        let mut grabbed = GrabbedPost::new_vec((posts, name.as_ref(), self.file_variant));
+        grabbed.extend(related);
+        self.push_collection(PostCollection::new(name, "Pools", grabbed));
 
         info!(
             "{} grabbed!",
             console::style(format!("\"{name}\"")).color256(39).italic()
         );
+
+        Ok(())
+    }
+
+    /// Grabs the parents and children of the given posts, up to the relationship depth of the group being grabbed.
+    ///
+    /// Each related post is named after its relation to the grabbed post it was found from (e.g `123_parent` or
+    /// `123_child_2`), with every further step added to the name (e.g `123_parent_child_1`). Posts that were already
+    /// grabbed are skipped, so relationships that loop back are only followed once.
+    ///
+    /// Related posts that can't be grabbed are logged and skipped, since the grabbed posts can still be downloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `posts`: The grabbed posts to follow the relationships of.
+    ///
+    /// returns: Vec<`GrabbedPost`, Global>
+    async fn grab_related(&self, posts: &[PostEntry]) -> Vec<GrabbedPost> {
+        let mut related = Vec::new();
+        if self.relationship_depth == 0 {
+            return related;
+        }
+
+        let mut seen: HashSet<i64> = posts.iter().map(|e| e.id).collect();
+        let mut current: Vec<(String, Relationships)> = posts
+            .iter()
+            .map(|e| (e.id.to_string(), e.relationships.clone()))
+            .collect();
+        for _ in 0..self.relationship_depth {
+            let mut relations: Vec<(i64, String)> = Vec::new();
+            for (name, relationships) in &current {
+                if let Some(parent_id) = relationships.parent_id
+                    && seen.insert(parent_id)
+                {
+                    relations.push((parent_id, format!("{name}_parent")));
+                }
+
+                for (i, child_id) in relationships.children.iter().enumerate() {
+                    if seen.insert(*child_id) {
+                        relations.push((*child_id, format!("{name}_child_{}", i + 1)));
+                    }
+                }
+            }
+
+            if relations.is_empty() {
+                break;
+            }
+
+            let entries = match self.search_by_ids(&relations).await {
+                Ok(entries) => entries,
+                Err(error) => {
+                    warn!("Skipping related posts as they could not be grabbed: {error}");
+                    break;
+                }
+            };
+
+            current = relations
+                .into_iter()
+                .filter_map(|(id, name)| {
+                    let entry = entries.get(&id)?;
+                    related.push(GrabbedPost::related(entry, &name, self.file_variant));
+                    Some((name, entry.relationships.clone()))
+                })
+                .collect();
+        }
+
+        if !related.is_empty() {
+            info!(
+                "Grabbed {} related posts...",
+                console::style(related.len()).cyan().italic()
+            );
+        }
+
+        related
+    }
+
+    /// Looks up the posts of the given relations in batches, keyed by their ID.
+    ///
+    /// Posts removed by the blacklist or without a file are left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `relations`: The IDs of the posts to look up, with the names of their relations.
+    ///
+    /// returns: Result<HashMap<i64, `PostEntry`>, E621Error>
+    async fn search_by_ids(&self, relations: &[(i64, String)]) -> Result<HashMap<i64, PostEntry>> {
+        let mut entries = HashMap::new();
+        for batch in relations.chunks(RELATED_BATCH_SIZE) {
+            let ids: Vec<String> = batch.iter().map(|(id, _)| id.to_string()).collect();
+            let mut posts = self
+                .request_sender
+                .bulk_search(&format!("id:{}", ids.join(",")), SearchPage::Numbered(1))
+                .await?
+                .posts;
+            self.filter_searched_posts(&mut posts, &mut FilteredPosts::default());
+            entries.extend(posts.into_iter().map(|e| (e.id, e)));
+        }
+
+        Ok(entries)
     }
 
     /// Sorts a pool by id based on the supplied [`PoolEntry`].
@@ -525,133 +1010,247 @@ impl Grabber {
         }
     }
 
-    /// Searches and grabs posts using the given tag.
-    ///
-    /// # Arguments
-    ///
-    /// * `tag`: The tag to use for the search.
-    ///
-    /// returns: Vec<`PostEntry`, Global>
-    fn get_posts_from_tag(&self, tag: &Tag) -> Vec<PostEntry> {
-        self.search(tag.name(), tag.search_type())
-    }
-
     /// Performs a search where it grabs posts.
     ///
     /// Depending on the given [`TagSearchType`], the way posts are grabs will be different.
-    /// - [General](TagSearchType::General) will search through pages only up to the [`POST_SEARCH_LIMIT`]
-    /// - [Special](TagSearchType::Special) will search repeatedly until there are no pages left to grab.
+    /// - [General](TagSearchType::General) will search through numbered pages until the limit is reached.
+    /// - [Special](TagSearchType::Special) will search repeatedly until there are no pages left to grab, until the limit
+    ///   is reached, or until it reaches the post ID given by `since`.
     ///
     /// # Arguments
     ///
     /// * `searching_tag`: The tag used for the search.
     /// * `tag_search_type`: The type of search to happen.
+    /// * `since`: The newest post downloaded from the search in an earlier run, if any.
+    /// * `limit`: How many posts are grabbed from the search.
     ///
-    /// returns: Vec<`PostEntry`, Global>
-    fn search(&self, searching_tag: &str, tag_search_type: &TagSearchType) -> Vec<PostEntry> {
+    /// returns: Result<Vec<`PostEntry`, Global>, E621Error>
+    async fn search(
+        &self,
+        searching_tag: &str,
+        tag_search_type: &TagSearchType,
+        since: Option<i64>,
+        limit: PostLimit,
+    ) -> Result<Vec<PostEntry>> {
         let mut posts: Vec<PostEntry> = Vec::new();
-        let mut filtered = 0;
-        let mut invalid_posts = 0;
+        let mut filtered = FilteredPosts::default();
         match tag_search_type {
             TagSearchType::General => {
-                posts = Vec::with_capacity(320 * POST_SEARCH_LIMIT as usize);
-                self.general_search(searching_tag, &mut posts, &mut filtered, &mut invalid_posts);
+                self.general_search(searching_tag, limit, &mut posts, &mut filtered)
+                    .await?;
             }
             TagSearchType::Special => {
-                self.special_search(searching_tag, &mut posts, &mut filtered, &mut invalid_posts);
+                self.special_search(searching_tag, since, limit, &mut posts, &mut filtered)
+                    .await?;
             }
             TagSearchType::None => {}
         }
 
-        if filtered > 0 {
-            info!(
-                "Filtered {} total blacklisted posts from search...",
-                console::style(filtered).cyan().italic()
-            );
-        }
-
-        if invalid_posts > 0 {
-            info!(
-                "Filtered {} total invalid posts from search...",
-                console::style(invalid_posts).cyan().italic()
-            );
-        }
-
-        posts
+        filtered.log();
+        Ok(posts)
     }
 
     /// Performs a special search to grab posts.
     ///
     /// The difference between special/general searches are this.
-    /// - Special searches aim to keep grabbing posts until there are not posts left to grab.
-    /// - General searches aim to grab only a few pages of posts (commonly 320 posts per page). You can refer to the
-    ///   [`POST_SEARCH_LIMIT`] for the current search limit of the general search.
+    /// - Special searches aim to keep grabbing posts until there are not posts left to grab, and are unlimited by
+    ///   default.
+    /// - General searches aim to grab only a few pages of posts (commonly 320 posts per page), which is 5 pages by
+    ///   default.
+    ///
+    /// Pages are walked with a cursor on the lowest post ID seen so far, so new posts arriving during the search don't
+    /// shift the results and there is no page limit. Searches with a custom order can't use the cursor and fall back to
+    /// page numbers, which stop at [`MAX_NUMBERED_PAGE`].
+    ///
+    /// When walking with the cursor, the search stops at `since` and only posts newer than it are grabbed. Searches with
+    /// a custom order aren't sorted by ID, so they are always walked in full.
     ///
     /// # Arguments
     ///
     /// * `searching_tag`: The tag to search for.
+    /// * `since`: The newest post downloaded from the search in an earlier run, if any.
+    /// * `limit`: How many posts are grabbed from the search.
     /// * `posts`:  The posts [Vec] to add searched posts into.
-    /// * `filtered`: The total amount of posts filtered.
-    /// * `invalid_posts`: The total amount of posts invalid by the [Blacklist].
-    fn special_search(
+    /// * `filtered`: The total amount of posts filtered from the search.
+    async fn special_search(
         &self,
         searching_tag: &str,
+        since: Option<i64>,
+        limit: PostLimit,
         posts: &mut Vec<PostEntry>,
-        filtered: &mut u16,
-        invalid_posts: &mut u16,
-    ) {
-        let mut page = 1;
+        filtered: &mut FilteredPosts,
+    ) -> Result<()> {
+        let custom_order = searching_tag
+            .split(' ')
+            .any(|e| e.starts_with("order:") || e.starts_with("ordfav:"));
+        let since = since.filter(|_| !custom_order);
+        let mut page = SearchPage::Numbered(1);
+        let mut pages = 0;
 
         loop {
-            let mut searched_posts = self.request_sender.bulk_search(searching_tag, page).posts;
-            if searched_posts.is_empty() {
+            let mut searched_posts = self
+                .request_sender
+                .bulk_search(searching_tag, page)
+                .await?
+                .posts;
+            let Some(lowest_id) = searched_posts.iter().map(|e| e.id).min() else {
                 break;
+            };
+            pages += 1;
+
+            let reached_since = since.is_some_and(|since| lowest_id <= since);
+            if let Some(since) = since {
+                searched_posts.retain(|e| e.id > since);
             }
 
-            *filtered += self.filter_posts_with_blacklist(&mut searched_posts);
-            *invalid_posts += Self::remove_invalid_posts(&mut searched_posts);
+            self.filter_searched_posts(&mut searched_posts, filtered);
+            Self::truncate_to_limit(limit, posts.len(), &mut searched_posts);
 
             searched_posts.reverse();
             posts.append(&mut searched_posts);
-            page += 1;
+            if reached_since {
+                trace!("Reached the last post downloaded from {searching_tag}...");
+                break;
+            }
+
+            if limit.reached(pages, posts.len()) {
+                trace!("Reached the limit of {limit} for {searching_tag}...");
+                break;
+            }
+
+            page = match page {
+                _ if !custom_order => SearchPage::Before(lowest_id),
+                SearchPage::Numbered(MAX_NUMBERED_PAGE) => {
+                    warn!(
+                        "Reached the last page the API allows for {searching_tag}, some posts may be missing..."
+                    );
+                    break;
+                }
+                SearchPage::Numbered(number) => SearchPage::Numbered(number + 1),
+                SearchPage::Before(_) => {
+                    unreachable!("Searches with a custom order only use page numbers")
+                }
+            };
         }
+
+        Ok(())
     }
 
     /// Performs a general search to grab posts.
     ///
     /// The difference between special/general searches are this.
-    /// - Special searches aim to keep grabbing posts until there are not posts left to grab.
-    /// - General searches aim to grab only a few pages of posts (commonly 320 posts per page). You can refer to the
-    ///   [`POST_SEARCH_LIMIT`] for the current search limit of the general search.
+    /// - Special searches aim to keep grabbing posts until there are not posts left to grab, and are unlimited by
+    ///   default.
+    /// - General searches aim to grab only a few pages of posts (commonly 320 posts per page), which is 5 pages by
+    ///   default.
+    ///
+    /// General searches use page numbers, so even without a limit they stop at [`MAX_NUMBERED_PAGE`].
     ///
     /// # Arguments
     ///
     /// * `searching_tag`: The tag to search for.
+    /// * `limit`: How many posts are grabbed from the search.
     /// * `posts`:  The posts [Vec] to add searched posts into.
-    /// * `filtered`: The total amount of posts filtered.
-    /// * `invalid_posts`: The total amount of posts invalid by the [Blacklist].
-    fn general_search(
+    /// * `filtered`: The total amount of posts filtered from the search.
+    async fn general_search(
         &self,
         searching_tag: &str,
+        limit: PostLimit,
         posts: &mut Vec<PostEntry>,
-        filtered: &mut u16,
-        invalid_posts: &mut u16,
-    ) {
-        for page in 1..POST_SEARCH_LIMIT {
+        filtered: &mut FilteredPosts,
+    ) -> Result<()> {
+        for page in 1..=MAX_NUMBERED_PAGE {
             let mut searched_posts: Vec<PostEntry> = self
                 .request_sender
-                .bulk_search(searching_tag, u16::from(page))
+                .bulk_search(searching_tag, SearchPage::Numbered(page))
+                .await?
                 .posts;
             if searched_posts.is_empty() {
                 break;
             }
 
-            *filtered += self.filter_posts_with_blacklist(&mut searched_posts);
-            *invalid_posts += Self::remove_invalid_posts(&mut searched_posts);
+            self.filter_searched_posts(&mut searched_posts, filtered);
+            Self::truncate_to_limit(limit, posts.len(), &mut searched_posts);
 
             searched_posts.reverse();
             posts.append(&mut searched_posts);
+            if limit.reached(page, posts.len()) {
+                trace!("Reached the limit of {limit} for {searching_tag}...");
+                break;
+            }
+
+            if page == MAX_NUMBERED_PAGE {
+                warn!(
+                    "Reached the last page the API allows for {searching_tag}, some posts may be missing..."
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drops the posts of a page that go past the limit, keeping the ones that come first in the search.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit`: How many posts are grabbed from the search.
+    /// * `grabbed`: How many posts were already grabbed from the search.
+    /// * `searched_posts`: The posts of the page, in the order of the search.
+    fn truncate_to_limit(limit: PostLimit, grabbed: usize, searched_posts: &mut Vec<PostEntry>) {
+        if let Some(remaining) = limit.remaining_posts(grabbed) {
+            searched_posts.truncate(remaining);
+        }
+    }
+
+    /// Removes the posts of a searched page that aren't grabbed, which are posts with a rating that isn't grabbed,
+    /// posts that violate the blacklist, invalid posts, and posts without a preview when only previews are grabbed.
+    ///
+    /// This is applied to every page searched, so every kind of search filters posts the same way.
+    ///
+    /// # Arguments
+    ///
+    /// * `posts`: The posts of the page.
+    /// * `filtered`: The total amount of posts filtered from the search.
+    fn filter_searched_posts(&self, posts: &mut Vec<PostEntry>, filtered: &mut FilteredPosts) {
+        filtered.rating += self.filter_posts_by_rating(posts);
+        filtered.blacklisted += self.filter_posts_with_blacklist(posts);
+        filtered.invalid += Self::remove_invalid_posts(posts);
+        filtered.no_preview += self.filter_posts_without_preview(posts);
+    }
+
+    /// Removes posts without a preview when only previews are grabbed, instead of downloading their original file.
+    ///
+    /// # Arguments
+    ///
+    /// * `posts`: The posts to check
+    ///
+    /// returns: u16
+    fn filter_posts_without_preview(&self, posts: &mut Vec<PostEntry>) -> u16 {
+        if self.file_variant != FileVariant::Preview {
+            return 0;
+        }
+
+        let before = posts.len();
+        posts.retain(|e| e.preview.url.is_some());
+        u16::try_from(before - posts.len()).unwrap_or(u16::MAX)
+    }
+
+    /// Removes posts with a rating that isn't grabbed.
+    ///
+    /// # Arguments
+    ///
+    /// * `posts`: The posts to check
+    ///
+    /// returns: u16
+    fn filter_posts_by_rating(&self, posts: &mut Vec<PostEntry>) -> u16 {
+        let ratings = self.ratings;
+        if ratings.allows_all() {
+            return 0;
         }
+
+        let before = posts.len();
+        posts.retain(|e| ratings.allows(&e.rating));
+        u16::try_from(before - posts.len()).unwrap_or(u16::MAX)
     }
 
     /// Checks through posts and removes any that violets the blacklist.
@@ -665,7 +1264,10 @@ impl Grabber {
         if self.request_sender.is_authenticated()
             && let Some(ref blacklist) = self.blacklist
         {
-            return blacklist.borrow_mut().filter_posts(posts);
+            return blacklist
+                .read()
+                .expect("Blacklist lock was poisoned!")
+                .filter_posts(posts);
         }
 
         0
@@ -721,3 +1323,152 @@ impl Grabber {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+
+    use super::*;
+    use crate::e621::sender::runtime::block_on;
+
+    /// Creates a grabber that replays every request from the recorded fixtures, along with the receiver its
+    /// collections are sent to.
+    fn replaying_grabber() -> (Grabber, UnboundedReceiver<PostCollection>) {
+        Config::initialize_for_tests();
+        Login::initialize_for_tests();
+        let request_sender =
+            RequestSender::new().expect("The request sender could not be created!");
+        let mut grabber = Grabber::new(request_sender);
+        let (sender, receiver) = unbounded_channel();
+        grabber.collections = Some(sender);
+        (grabber, receiver)
+    }
+
+    /// Gets the names of the posts in a collection.
+    fn post_names(collection: &PostCollection) -> Vec<&str> {
+        collection.posts().iter().map(GrabbedPost::name).collect()
+    }
+
+    #[test]
+    fn grab_pool_keeps_pool_order() {
+        let (mut grabber, mut collections) = replaying_grabber();
+        let tag = Tag::new("1234", TagSearchType::Special, TagType::Pool);
+        block_on(grabber.grab_pool(&tag)).expect("The pool could not be grabbed!");
+
+        let collection = collections.try_recv().expect("No collection was grabbed!");
+        assert_eq!(collection.name(), "Test_Pool");
+        assert_eq!(collection.category(), "Pools");
+        assert_eq!(
+            post_names(&collection),
+            [
+                "Test_Pool Page_00001.png",
+                "Test_Pool Page_00002.jpg",
+                "Test_Pool Page_00003.png"
+            ]
+        );
+        assert_eq!(
+            collection.posts()[1].url(),
+            "https://static1.e621.net/data/c3/c3/c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3.jpg"
+        );
+        assert!(collection.sync_mark().is_none());
+    }
+
+    #[test]
+    fn grab_set_names_posts_by_md5() {
+        let (mut grabber, mut collections) = replaying_grabber();
+        let tag = Tag::new("500", TagSearchType::Special, TagType::Set);
+        block_on(grabber.grab_set(&tag)).expect("The set could not be grabbed!");
+
+        let collection = collections.try_recv().expect("No collection was grabbed!");
+        assert_eq!(collection.name(), "Test Set");
+        assert_eq!(collection.category(), "Sets");
+        assert_eq!(
+            post_names(&collection),
+            [
+                "a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1.png",
+                "a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2.png",
+                "a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3.webm"
+            ]
+        );
+        assert_eq!(collection.posts()[0].file_size(), Some(1024));
+        assert_eq!(
+            collection.posts()[0].md5(),
+            Some("a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1")
+        );
+        assert_eq!(
+            collection.sync_mark(),
+            Some(&(grabber.sync_key("set:test_set", PostLimit::All), 103))
+        );
+    }
+
+    #[test]
+    fn sync_key_changes_with_filters() {
+        let (mut grabber, _) = replaying_grabber();
+        let key = grabber.sync_key("set:test_set", PostLimit::All);
+        assert!(key.starts_with("set:test_set ["));
+        assert_eq!(key, grabber.sync_key("set:test_set", PostLimit::All));
+        assert_ne!(key, grabber.sync_key("set:test_set", PostLimit::Posts(100)));
+
+        grabber.ratings = "s".parse().expect("The ratings could not be parsed!");
+        assert_ne!(key, grabber.sync_key("set:test_set", PostLimit::All));
+    }
+
+    #[test]
+    fn grab_set_filters_blacklisted_posts() {
+        let (mut grabber, mut collections) = replaying_grabber();
+        let mut blacklist = Blacklist::new(grabber.request_sender.clone());
+        blacklist
+            .parse_blacklist(String::from(
+                "gore\nuser:bad_uploader\nmissing_user_line user:nobody",
+            ))
+            .expect("The blacklist could not be parsed!")
+            .cache_users();
+        grabber.set_blacklist(Arc::new(RwLock::new(blacklist)));
+
+        let tag = Tag::new("500", TagSearchType::Special, TagType::Set);
+        block_on(grabber.grab_set(&tag)).expect("The set could not be grabbed!");
+
+        let collection = collections.try_recv().expect("No collection was grabbed!");
+        assert_eq!(
+            post_names(&collection),
+            ["a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1.png"]
+        );
+    }
+
+    #[test]
+    fn grabbed_posts_follow_naming_convention_and_variant() {
+        let (grabber, _) = replaying_grabber();
+        let posts = block_on(
+            grabber
+                .request_sender
+                .bulk_search("set:test_set", SearchPage::Numbered(1)),
+        )
+        .expect("The set could not be searched!")
+        .posts;
+        let post = posts
+            .into_iter()
+            .find(|e| e.id == 101)
+            .expect("The post is missing from the fixture!");
+
+        let by_id = GrabbedPost::from((post.clone(), "ID", FileVariant::Original));
+        assert_eq!(by_id.name(), "101.png");
+
+        let custom = GrabbedPost::from((
+            post.clone(),
+            "{artist} - {id} ({rating})",
+            FileVariant::Original,
+        ));
+        assert_eq!(custom.name(), "artist_one - 101 (s).png");
+
+        let sample = GrabbedPost::from((post.clone(), "md5", FileVariant::Sample));
+        assert_eq!(sample.name(), "a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1_sample.jpg");
+        assert_eq!(
+            sample.url(),
+            "https://static1.e621.net/data/sample/a1/a1/a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1.jpg"
+        );
+        assert_eq!(sample.file_size(), None);
+
+        let related = GrabbedPost::related(&post, "100_child_1", FileVariant::Original);
+        assert_eq!(related.name(), "100_child_1.png");
+    }
+}
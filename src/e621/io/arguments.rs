@@ -0,0 +1,124 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::env::args;
+use std::sync::OnceLock;
+
+use anyhow::{Error, anyhow};
+
+/// The usage message printed when the arguments can't be parsed.
+const USAGE: &str = "\
+Usage: e621_downloader [OPTIONS]
+
+Options:
+  --base-url <URL>        Sends all API calls to this host instead of the one in the config
+  --refresh-tags          Looks up every tag again instead of using the tag cache
+  --full-sync             Walks every search in full instead of stopping at the last post downloaded from it
+  --ratings <RATINGS>     Grabs only these ratings (e.g s or sq) instead of the ones in the config
+  -h, --help              Prints this message";
+
+/// Options passed to the program through the command line, which override the ones in the config.
+#[derive(Debug, Default)]
+pub(crate) struct Arguments {
+    /// The host all API calls are sent to.
+    base_url: Option<String>,
+    /// Whether every tag is looked up again instead of using the tag cache.
+    refresh_tags: bool,
+    /// Whether every search is walked in full instead of stopping at the last post downloaded from it.
+    full_sync: bool,
+    /// Which ratings of posts are grabbed.
+    ratings: Option<String>,
+}
+
+static ARGUMENTS: OnceLock<Arguments> = OnceLock::new();
+
+impl Arguments {
+    /// The host all API calls are sent to.
+    pub(crate) fn base_url(&self) -> Option<&str> {
+        self.base_url.as_deref()
+    }
+
+    /// Whether every tag is looked up again instead of using the tag cache.
+    pub(crate) fn refresh_tags(&self) -> bool {
+        self.refresh_tags
+    }
+
+    /// Whether every search is walked in full instead of stopping at the last post downloaded from it.
+    pub(crate) fn full_sync(&self) -> bool {
+        self.full_sync
+    }
+
+    /// Which ratings of posts are grabbed.
+    pub(crate) fn ratings(&self) -> Option<&str> {
+        self.ratings.as_deref()
+    }
+
+    /// Gets the global instance of [Arguments].
+    pub(crate) fn get() -> &'static Self {
+        ARGUMENTS
+            .get()
+            .expect("Arguments have not been initialized!")
+    }
+
+    /// Parses the command line and initializes the global `Arguments` instance.
+    ///
+    /// If `--help` is passed, the usage is printed and the program exits.
+    pub(crate) fn initialize() -> Result<(), Error> {
+        let arguments = Self::parse(args().skip(1))?;
+        ARGUMENTS
+            .set(arguments)
+            .map_err(|_| anyhow!("Arguments have already been initialized!"))?;
+        Ok(())
+    }
+
+    /// Parses the arguments given.
+    ///
+    /// # Arguments
+    ///
+    /// * `arguments`: The arguments to parse, without the program name.
+    ///
+    /// returns: Result<Arguments, Error>
+    fn parse(mut arguments: impl Iterator<Item = String>) -> Result<Self, Error> {
+        let mut parsed = Arguments::default();
+        while let Some(argument) = arguments.next() {
+            let (name, inline_value) = match argument.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (argument, None),
+            };
+
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| arguments.next())
+                    .ok_or_else(|| anyhow!("Missing value for `{name}`!\n\n{USAGE}"))
+            };
+
+            match name.as_str() {
+                "--base-url" => parsed.base_url = Some(value()?),
+                "--refresh-tags" => parsed.refresh_tags = true,
+                "--full-sync" => parsed.full_sync = true,
+                "--ratings" => parsed.ratings = Some(value()?),
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                _ => return Err(anyhow!("Unknown argument `{name}`!\n\n{USAGE}")),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
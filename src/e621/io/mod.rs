@@ -14,18 +14,28 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::fs::{read_to_string, write};
-use std::io;
 use std::path::Path;
-use std::process::exit;
+use std::str::FromStr;
 use std::sync::OnceLock;
+use std::time::Duration;
 
 use anyhow::{Context, Error};
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use serde_json::{from_str, to_string_pretty};
 
+use crate::e621::io::arguments::Arguments;
+use crate::e621::io::tag::TagSearchType;
+
+pub(crate) mod arguments;
 pub(crate) mod parser;
+pub(crate) mod state_file;
+pub(crate) mod sync_state;
 pub(crate) mod tag;
+pub(crate) mod tag_cache;
 
 /// Name of the configuration file.
 pub(crate) const CONFIG_NAME: &str = "config.json";
@@ -42,6 +52,790 @@ pub(crate) struct Config {
     /// The file naming convention (e.g "md5", "id").
     #[serde(rename = "fileNamingConvention")]
     naming_convention: String,
+    /// The maximum amount of posts downloaded at the same time.
+    #[serde(
+        rename = "maxConcurrentDownloads",
+        default = "default_max_concurrent_downloads"
+    )]
+    max_concurrent_downloads: usize,
+    /// The rate limit applied to API calls.
+    #[serde(rename = "apiRateLimit", default = "RateLimit::default_api")]
+    api_rate_limit: RateLimit,
+    /// The rate limit applied to file downloads.
+    #[serde(rename = "downloadRateLimit", default = "RateLimit::default_download")]
+    download_rate_limit: RateLimit,
+    /// How failed requests are retried.
+    #[serde(rename = "retry", default)]
+    retry_policy: RetryPolicy,
+    /// The host all API calls are sent to (e.g "https://e621.net").
+    #[serde(rename = "apiBaseUrl", default = "default_api_base_url")]
+    api_base_url: String,
+    /// Paths that replace the default path of an endpoint, keyed by the endpoint (e.g "posts": "/posts.json").
+    #[serde(default)]
+    endpoints: HashMap<String, String>,
+    /// Where every request and response is recorded to, or replayed from.
+    #[serde(default)]
+    cassette: CassetteConfig,
+    /// The proxy every request is sent through, if any.
+    #[serde(default)]
+    proxy: Option<ProxyConfig>,
+    /// The bandwidth cap applied to file downloads, and the times of day where it changes.
+    #[serde(default)]
+    bandwidth: BandwidthConfig,
+    /// How many hours a tag lookup is cached for, or `0` to disable the tag cache.
+    #[serde(rename = "tagCacheTtlHours", default = "default_tag_cache_ttl_hours")]
+    tag_cache_ttl_hours: u64,
+    /// Which variant of a post's file is downloaded, unless a group overrides it.
+    #[serde(rename = "fileVariant", default)]
+    file_variant: FileVariant,
+    /// How long requests and downloads can take before they are aborted and retried.
+    #[serde(default)]
+    timeouts: TimeoutConfig,
+    /// Which HTTP version requests are sent with.
+    #[serde(default)]
+    protocol: ProtocolMode,
+    /// The file every request sent during the run is saved to in the HAR format, if any.
+    #[serde(rename = "harFile", default)]
+    har_file: Option<String>,
+    /// Whether searches stop at the newest post downloaded from them in an earlier run.
+    #[serde(rename = "incrementalSync", default)]
+    incremental_sync: bool,
+    /// How many posts are grabbed from each search, unless a group or tag overrides it.
+    #[serde(rename = "searchLimits", default)]
+    search_limits: SearchLimits,
+    /// How a post that appears in more than one collection is saved to the locations after the first.
+    #[serde(rename = "duplicateMode", default)]
+    duplicate_mode: DuplicateMode,
+    /// How many steps of parents and children are grabbed along with each post, or `0` to not grab them.
+    #[serde(rename = "relationshipDepth", default)]
+    relationship_depth: u8,
+    /// Which ratings of posts are grabbed, unless a group overrides it.
+    #[serde(default)]
+    ratings: RatingFilter,
+}
+
+/// A rate limit for a kind of request, in the form of a token bucket.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct RateLimit {
+    /// How many requests can be sent per second over time.
+    #[serde(rename = "requestsPerSecond")]
+    requests_per_second: f64,
+    /// How many requests can be sent at once before the limit kicks in.
+    burst: u32,
+}
+
+impl RateLimit {
+    /// How many requests can be sent per second over time.
+    pub(crate) fn requests_per_second(&self) -> f64 {
+        self.requests_per_second
+    }
+
+    /// How many requests can be sent at once before the limit kicks in.
+    pub(crate) fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// The default limit for API calls, which keeps the downloader under e621's limit of two requests per second.
+    fn default_api() -> Self {
+        RateLimit {
+            requests_per_second: 2.0,
+            burst: 1,
+        }
+    }
+
+    /// The default limit for file downloads.
+    fn default_download() -> Self {
+        RateLimit {
+            requests_per_second: 8.0,
+            burst: 4,
+        }
+    }
+
+    /// Checks that the limit is able to let requests through.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the option being checked.
+    fn validate(&self, name: &str) -> Result<(), Error> {
+        if self.requests_per_second <= 0.0 || self.burst == 0 {
+            return Err(anyhow::anyhow!(
+                "{name} must allow at least one request (requestsPerSecond > 0, burst >= 1)!"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// How requests that failed for a temporary reason are retried.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct RetryPolicy {
+    /// The maximum amount of times a request is sent before giving up.
+    #[serde(rename = "maxAttempts")]
+    max_attempts: u32,
+    /// How long to wait before the first retry, in milliseconds. This doubles with every attempt.
+    #[serde(rename = "initialDelayMs")]
+    initial_delay_ms: u64,
+    /// The longest time to wait between two attempts, in milliseconds.
+    #[serde(rename = "maxDelayMs")]
+    max_delay_ms: u64,
+}
+
+impl RetryPolicy {
+    /// The maximum amount of times a request is sent before giving up.
+    pub(crate) fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// How long to wait before the first retry.
+    pub(crate) fn initial_delay(&self) -> Duration {
+        Duration::from_millis(self.initial_delay_ms)
+    }
+
+    /// The longest time to wait between two attempts.
+    pub(crate) fn max_delay(&self) -> Duration {
+        Duration::from_millis(self.max_delay_ms)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_delay_ms: 1000,
+            max_delay_ms: 60000,
+        }
+    }
+}
+
+/// Which HTTP version requests are sent with.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ProtocolMode {
+    /// HTTP/2 is used when the host offers it through ALPN, and HTTP/1.1 otherwise.
+    #[default]
+    Auto,
+    /// Only HTTP/1.1 is used.
+    Http1,
+    /// HTTP/2 is assumed without negotiating it first (prior knowledge), which also works for plain `http` hosts
+    /// that support it.
+    Http2,
+}
+
+/// Which ratings of posts are grabbed.
+///
+/// This is written as the letters of the ratings to grab, `s` for safe, `q` for questionable and `e` for explicit (e.g
+/// `sq` grabs safe and questionable posts).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) struct RatingFilter {
+    /// Whether safe posts are grabbed.
+    safe: bool,
+    /// Whether questionable posts are grabbed.
+    questionable: bool,
+    /// Whether explicit posts are grabbed.
+    explicit: bool,
+}
+
+impl RatingFilter {
+    /// Checks if posts with the given rating are grabbed.
+    ///
+    /// # Arguments
+    ///
+    /// * `rating`: The rating of a post (`s`, `q` or `e`).
+    ///
+    /// returns: bool
+    pub(crate) fn allows(&self, rating: &str) -> bool {
+        match rating {
+            "s" => self.safe,
+            "q" => self.questionable,
+            "e" => self.explicit,
+            _ => true,
+        }
+    }
+
+    /// Whether posts of every rating are grabbed.
+    pub(crate) fn allows_all(&self) -> bool {
+        self.safe && self.questionable && self.explicit
+    }
+}
+
+impl Default for RatingFilter {
+    fn default() -> Self {
+        RatingFilter {
+            safe: true,
+            questionable: true,
+            explicit: true,
+        }
+    }
+}
+
+impl FromStr for RatingFilter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = RatingFilter {
+            safe: false,
+            questionable: false,
+            explicit: false,
+        };
+        for rating in s.chars().filter(|e| !e.is_whitespace() && *e != ',') {
+            match rating.to_ascii_lowercase() {
+                's' => filter.safe = true,
+                'q' => filter.questionable = true,
+                'e' => filter.explicit = true,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "\"{s}\" is not a rating filter, expected any of s, q and e (e.g sq)!"
+                    ));
+                }
+            }
+        }
+
+        if !filter.safe && !filter.questionable && !filter.explicit {
+            return Err(anyhow::anyhow!(
+                "A rating filter must contain at least one of s, q and e!"
+            ));
+        }
+
+        Ok(filter)
+    }
+}
+
+impl TryFrom<String> for RatingFilter {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl Display for RatingFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ratings = [
+            (self.safe, 's'),
+            (self.questionable, 'q'),
+            (self.explicit, 'e'),
+        ];
+        for (_, rating) in ratings.iter().filter(|(allowed, _)| *allowed) {
+            write!(f, "{rating}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<RatingFilter> for String {
+    fn from(value: RatingFilter) -> Self {
+        value.to_string()
+    }
+}
+
+/// How a post that appears in more than one collection is saved to the locations after the first, since it's only
+/// downloaded once.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum DuplicateMode {
+    /// A hard link to the downloaded file is created, or a copy if the file system doesn't support them.
+    #[default]
+    Hardlink,
+    /// A symbolic link to the downloaded file is created.
+    Symlink,
+    /// The downloaded file is copied.
+    Copy,
+}
+
+/// How long requests and downloads can take before they are aborted and retried.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct TimeoutConfig {
+    /// How many seconds connecting to a host can take.
+    #[serde(rename = "connectSecs", default = "default_connect_secs")]
+    connect_secs: u64,
+    /// How many seconds can pass without receiving any data before a request is considered stalled.
+    #[serde(rename = "readIdleSecs", default = "default_read_idle_secs")]
+    read_idle_secs: u64,
+    /// How many seconds an API call can take in total.
+    #[serde(rename = "requestSecs", default = "default_request_secs")]
+    request_secs: u64,
+    /// The slowest average speed a download can have, which scales its overall timeout with its size. Downloads have
+    /// no overall timeout if not set.
+    #[serde(rename = "downloadMinBytesPerSecond", default)]
+    download_min_bytes_per_second: Option<u64>,
+}
+
+impl TimeoutConfig {
+    /// How long connecting to a host can take.
+    pub(crate) fn connect(&self) -> Duration {
+        Duration::from_secs(self.connect_secs)
+    }
+
+    /// How long can pass without receiving any data before a request is considered stalled.
+    pub(crate) fn read_idle(&self) -> Duration {
+        Duration::from_secs(self.read_idle_secs)
+    }
+
+    /// How long an API call can take in total.
+    pub(crate) fn request(&self) -> Duration {
+        Duration::from_secs(self.request_secs)
+    }
+
+    /// How long downloading the given amount of bytes can take in total, or `None` if downloads have no overall
+    /// timeout.
+    ///
+    /// This is `requestSecs` plus the time the bytes take at `downloadMinBytesPerSecond`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes`: The amount of bytes left to download.
+    ///
+    /// returns: Option<Duration>
+    pub(crate) fn download(&self, bytes: u64) -> Option<Duration> {
+        self.download_min_bytes_per_second
+            .map(|rate| self.request() + Duration::from_secs_f64(bytes as f64 / rate as f64))
+    }
+
+    /// Checks that every timeout is above zero.
+    fn validate(&self) -> Result<(), Error> {
+        if self.connect_secs == 0 || self.read_idle_secs == 0 || self.request_secs == 0 {
+            return Err(anyhow::anyhow!(
+                "timeouts.connectSecs, timeouts.readIdleSecs and timeouts.requestSecs must be above 0!"
+            ));
+        }
+
+        if self.download_min_bytes_per_second == Some(0) {
+            return Err(anyhow::anyhow!(
+                "timeouts.downloadMinBytesPerSecond must be above 0, remove it to disable the download timeout!"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        TimeoutConfig {
+            connect_secs: default_connect_secs(),
+            read_idle_secs: default_read_idle_secs(),
+            request_secs: default_request_secs(),
+            download_min_bytes_per_second: None,
+        }
+    }
+}
+
+/// Which variant of a post's file is downloaded.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum FileVariant {
+    /// The original file that was uploaded.
+    #[default]
+    Original,
+    /// The downscaled sample of the file, or the original if the post has no sample.
+    Sample,
+    /// The small preview thumbnail of the file.
+    Preview,
+}
+
+impl FileVariant {
+    /// The suffix added to the name of a downloaded file of this variant.
+    ///
+    /// returns: &str
+    pub(crate) fn suffix(self) -> &'static str {
+        match self {
+            FileVariant::Original => "",
+            FileVariant::Sample => "_sample",
+            FileVariant::Preview => "_preview",
+        }
+    }
+}
+
+impl FromStr for FileVariant {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "original" => Ok(FileVariant::Original),
+            "sample" => Ok(FileVariant::Sample),
+            "preview" => Ok(FileVariant::Preview),
+            _ => Err(anyhow::anyhow!(
+                "\"{s}\" is not a file variant, expected original, sample or preview!"
+            )),
+        }
+    }
+}
+
+/// How many posts are grabbed from a search.
+///
+/// This is written as `all`, a number of posts (e.g `100` or `newest 100`), or a number of pages (e.g `5 pages`). Spaces
+/// are optional, so `5pages` can be used in the tag file.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) enum PostLimit {
+    /// Every post is grabbed.
+    All,
+    /// Only the given amount of posts are grabbed, newest first.
+    Posts(usize),
+    /// Only the given amount of pages are grabbed.
+    Pages(u16),
+}
+
+impl PostLimit {
+    /// Checks if the limit is reached after the given amount of pages and posts were grabbed.
+    ///
+    /// # Arguments
+    ///
+    /// * `pages`: How many pages were searched.
+    /// * `posts`: How many posts were grabbed.
+    ///
+    /// returns: bool
+    pub(crate) fn reached(&self, pages: u16, posts: usize) -> bool {
+        match *self {
+            PostLimit::All => false,
+            PostLimit::Posts(limit) => posts >= limit,
+            PostLimit::Pages(limit) => pages >= limit,
+        }
+    }
+
+    /// How many more posts can be grabbed after the given amount of posts were grabbed, or `None` if there is no
+    /// limit on the amount of posts.
+    ///
+    /// # Arguments
+    ///
+    /// * `posts`: How many posts were grabbed.
+    ///
+    /// returns: Option<usize>
+    pub(crate) fn remaining_posts(&self, posts: usize) -> Option<usize> {
+        match *self {
+            PostLimit::Posts(limit) => Some(limit.saturating_sub(posts)),
+            PostLimit::All | PostLimit::Pages(_) => None,
+        }
+    }
+}
+
+impl FromStr for PostLimit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let limit: String = s
+            .split_whitespace()
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if limit == "all" {
+            return Ok(PostLimit::All);
+        }
+
+        let invalid = || {
+            anyhow::anyhow!(
+                "\"{s}\" is not a post limit, expected all, a number of posts (e.g 100) or a number of pages (e.g 5 pages)!"
+            )
+        };
+        let limit = limit.strip_prefix("newest").unwrap_or(&limit);
+        let parsed = match limit
+            .strip_suffix("pages")
+            .or_else(|| limit.strip_suffix("page"))
+        {
+            Some(pages) => PostLimit::Pages(pages.parse().map_err(|_| invalid())?),
+            None => PostLimit::Posts(limit.parse().map_err(|_| invalid())?),
+        };
+        if matches!(parsed, PostLimit::Posts(0) | PostLimit::Pages(0)) {
+            return Err(anyhow::anyhow!(
+                "\"{s}\" must be above 0, use all to remove the limit!"
+            ));
+        }
+
+        Ok(parsed)
+    }
+}
+
+impl TryFrom<String> for PostLimit {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl Display for PostLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostLimit::All => write!(f, "all"),
+            PostLimit::Posts(posts) => write!(f, "{posts}"),
+            PostLimit::Pages(1) => write!(f, "1 page"),
+            PostLimit::Pages(pages) => write!(f, "{pages} pages"),
+        }
+    }
+}
+
+impl From<PostLimit> for String {
+    fn from(value: PostLimit) -> Self {
+        value.to_string()
+    }
+}
+
+/// How many posts are grabbed from each kind of search.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct SearchLimits {
+    /// The limit for general searches, which are tags with too many posts to grab in full.
+    #[serde(default = "default_general_limit")]
+    general: PostLimit,
+    /// The limit for special searches, which are artists, smaller tags, favorites, pools and sets.
+    #[serde(default = "default_special_limit")]
+    special: PostLimit,
+}
+
+impl SearchLimits {
+    /// The limit for the given kind of search.
+    ///
+    /// # Arguments
+    ///
+    /// * `search_type`: The kind of search.
+    ///
+    /// returns: `PostLimit`
+    pub(crate) fn for_search(&self, search_type: &TagSearchType) -> PostLimit {
+        match search_type {
+            TagSearchType::General => self.general,
+            TagSearchType::Special | TagSearchType::None => self.special,
+        }
+    }
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {
+            general: default_general_limit(),
+            special: default_special_limit(),
+        }
+    }
+}
+
+/// Whether requests are sent as normal, recorded to a cassette, or replayed from one.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CassetteMode {
+    /// Requests are sent to the server and nothing is recorded.
+    #[default]
+    Off,
+    /// Requests are sent to the server and every response is saved to the cassette.
+    Record,
+    /// Nothing is sent to the server, every response is loaded from the cassette.
+    Replay,
+}
+
+/// Where requests are recorded to, or replayed from.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct CassetteConfig {
+    /// Whether requests are recorded, replayed, or sent as normal.
+    mode: CassetteMode,
+    /// The directory the cassette is saved in.
+    directory: String,
+}
+
+impl CassetteConfig {
+    /// Whether requests are recorded, replayed, or sent as normal.
+    pub(crate) fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// The directory the cassette is saved in.
+    pub(crate) fn directory(&self) -> &str {
+        &self.directory
+    }
+}
+
+impl Default for CassetteConfig {
+    fn default() -> Self {
+        CassetteConfig {
+            mode: CassetteMode::Off,
+            directory: String::from("cassettes"),
+        }
+    }
+}
+
+/// A proxy that every request is sent through.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct ProxyConfig {
+    /// The url of the proxy (e.g "http://proxy:8080", "socks5://proxy:1080").
+    url: String,
+    /// The username to log in to the proxy with.
+    #[serde(default)]
+    username: Option<String>,
+    /// The password to log in to the proxy with.
+    #[serde(default)]
+    password: Option<String>,
+    /// Hosts that are reached directly instead of through the proxy (e.g "localhost", "*.example.com").
+    #[serde(rename = "noProxy", default)]
+    no_proxy: Vec<String>,
+}
+
+impl ProxyConfig {
+    /// The url of the proxy.
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The username to log in to the proxy with.
+    pub(crate) fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    /// The password to log in to the proxy with.
+    pub(crate) fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
+    /// Hosts that are reached directly instead of through the proxy.
+    pub(crate) fn no_proxy(&self) -> &[String] {
+        &self.no_proxy
+    }
+
+    /// The url of the proxy with any credentials removed, so it can be logged.
+    pub(crate) fn redacted_url(&self) -> String {
+        match Url::parse(&self.url) {
+            Ok(mut url) => {
+                let _ = url.set_username("");
+                let _ = url.set_password(None);
+                url.to_string()
+            }
+            Err(_) => self.url.clone(),
+        }
+    }
+
+    /// Checks that the proxy url can be used.
+    fn validate(&self) -> Result<(), Error> {
+        let url = Url::parse(&self.url).context("proxy.url is not a valid url!")?;
+        if !matches!(
+            url.scheme(),
+            "http" | "https" | "socks4" | "socks4a" | "socks5" | "socks5h"
+        ) {
+            return Err(anyhow::anyhow!(
+                "proxy.url must be an http, https, socks4 or socks5 url!"
+            ));
+        }
+
+        if self.password.is_some() && self.username.is_none() {
+            return Err(anyhow::anyhow!(
+                "proxy.password is set without a proxy.username!"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The bandwidth cap applied to file downloads.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct BandwidthConfig {
+    /// The maximum bytes downloaded per second outside of any window, or unlimited if not set.
+    #[serde(rename = "bytesPerSecond", default)]
+    bytes_per_second: Option<u64>,
+    /// Times of day where a different cap, or a pause, applies instead.
+    #[serde(default)]
+    windows: Vec<TransferWindow>,
+}
+
+impl BandwidthConfig {
+    /// The maximum bytes downloaded per second outside of any window, or unlimited if not set.
+    pub(crate) fn bytes_per_second(&self) -> Option<u64> {
+        self.bytes_per_second
+    }
+
+    /// Times of day where a different cap, or a pause, applies instead.
+    pub(crate) fn windows(&self) -> &[TransferWindow] {
+        &self.windows
+    }
+
+    /// Checks that the caps can let bytes through and that every window is valid.
+    fn validate(&self) -> Result<(), Error> {
+        if self.bytes_per_second == Some(0) {
+            return Err(anyhow::anyhow!(
+                "bandwidth.bytesPerSecond must be above 0, use a window with \"pause\" to stop downloads!"
+            ));
+        }
+
+        self.windows.iter().try_for_each(TransferWindow::validate)
+    }
+}
+
+/// A time of day where a different bandwidth cap, or a pause, applies to file downloads.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct TransferWindow {
+    /// The local time the window starts at (e.g "09:00").
+    start: String,
+    /// The local time the window ends at (e.g "17:30"). A window ending before it starts runs past midnight.
+    end: String,
+    /// The maximum bytes downloaded per second during the window, or unlimited if not set.
+    #[serde(rename = "bytesPerSecond", default)]
+    bytes_per_second: Option<u64>,
+    /// Whether downloads are paused during the window.
+    #[serde(default)]
+    pause: bool,
+}
+
+impl TransferWindow {
+    /// The maximum bytes downloaded per second during the window, or unlimited if not set.
+    pub(crate) fn bytes_per_second(&self) -> Option<u64> {
+        self.bytes_per_second
+    }
+
+    /// Whether downloads are paused during the window.
+    pub(crate) fn is_paused(&self) -> bool {
+        self.pause
+    }
+
+    /// The local time the window ends at.
+    pub(crate) fn end(&self) -> &str {
+        &self.end
+    }
+
+    /// Checks whether a time of day falls inside the window.
+    ///
+    /// # Arguments
+    ///
+    /// * `minute_of_day`: The minutes since midnight.
+    ///
+    /// returns: bool
+    pub(crate) fn contains(&self, minute_of_day: u16) -> bool {
+        let (Ok(start), Ok(end)) = (parse_time_of_day(&self.start), parse_time_of_day(&self.end))
+        else {
+            return false;
+        };
+
+        if start <= end {
+            (start..end).contains(&minute_of_day)
+        } else {
+            minute_of_day >= start || minute_of_day < end
+        }
+    }
+
+    /// Checks that the start and end of the window are valid times of day.
+    fn validate(&self) -> Result<(), Error> {
+        parse_time_of_day(&self.start)?;
+        parse_time_of_day(&self.end)?;
+        if self.bytes_per_second == Some(0) {
+            return Err(anyhow::anyhow!(
+                "bandwidth window {}-{} must have a bytesPerSecond above 0, use \"pause\" to stop downloads!",
+                self.start,
+                self.end
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a time of day in the form of `HH:MM` into the minutes since midnight.
+///
+/// # Arguments
+///
+/// * `time`: The time to parse.
+///
+/// returns: Result<u16, Error>
+fn parse_time_of_day(time: &str) -> Result<u16, Error> {
+    time.split_once(':')
+        .and_then(|(hour, minute)| Some((hour.parse::<u16>().ok()?, minute.parse::<u16>().ok()?)))
+        .filter(|&(hour, minute)| hour < 24 && minute < 60)
+        .map(|(hour, minute)| hour * 60 + minute)
+        .ok_or_else(|| anyhow::anyhow!("\"{time}\" is not a valid time of day, use HH:MM!"))
 }
 
 static CONFIG: OnceLock<Config> = OnceLock::new();
@@ -57,6 +851,102 @@ impl Config {
         &self.naming_convention
     }
 
+    /// The maximum amount of posts downloaded at the same time.
+    pub(crate) fn max_concurrent_downloads(&self) -> usize {
+        self.max_concurrent_downloads
+    }
+
+    /// The rate limit applied to API calls.
+    pub(crate) fn api_rate_limit(&self) -> &RateLimit {
+        &self.api_rate_limit
+    }
+
+    /// The rate limit applied to file downloads.
+    pub(crate) fn download_rate_limit(&self) -> &RateLimit {
+        &self.download_rate_limit
+    }
+
+    /// How failed requests are retried.
+    pub(crate) fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// The host all API calls are sent to.
+    pub(crate) fn api_base_url(&self) -> &str {
+        &self.api_base_url
+    }
+
+    /// Paths that replace the default path of an endpoint, keyed by the endpoint.
+    pub(crate) fn endpoints(&self) -> &HashMap<String, String> {
+        &self.endpoints
+    }
+
+    /// Where every request and response is recorded to, or replayed from.
+    pub(crate) fn cassette(&self) -> &CassetteConfig {
+        &self.cassette
+    }
+
+    /// The proxy every request is sent through, if any.
+    pub(crate) fn proxy(&self) -> Option<&ProxyConfig> {
+        self.proxy.as_ref()
+    }
+
+    /// The bandwidth cap applied to file downloads.
+    pub(crate) fn bandwidth(&self) -> &BandwidthConfig {
+        &self.bandwidth
+    }
+
+    /// How long a tag lookup is cached for, or `None` if the tag cache is disabled.
+    pub(crate) fn tag_cache_ttl(&self) -> Option<Duration> {
+        (self.tag_cache_ttl_hours > 0)
+            .then(|| Duration::from_secs(self.tag_cache_ttl_hours * 60 * 60))
+    }
+
+    /// Which variant of a post's file is downloaded, unless a group overrides it.
+    pub(crate) fn file_variant(&self) -> FileVariant {
+        self.file_variant
+    }
+
+    /// How long requests and downloads can take before they are aborted and retried.
+    pub(crate) fn timeouts(&self) -> &TimeoutConfig {
+        &self.timeouts
+    }
+
+    /// Which HTTP version requests are sent with.
+    pub(crate) fn protocol(&self) -> ProtocolMode {
+        self.protocol
+    }
+
+    /// The file every request sent during the run is saved to in the HAR format, if any.
+    pub(crate) fn har_file(&self) -> Option<&str> {
+        self.har_file.as_deref()
+    }
+
+    /// Whether searches stop at the newest post downloaded from them in an earlier run.
+    pub(crate) fn incremental_sync(&self) -> bool {
+        self.incremental_sync
+    }
+
+    /// How many posts are grabbed from each search, unless a group or tag overrides it.
+    pub(crate) fn search_limits(&self) -> &SearchLimits {
+        &self.search_limits
+    }
+
+    /// How a post that appears in more than one collection is saved to the locations after the first.
+    pub(crate) fn duplicate_mode(&self) -> DuplicateMode {
+        self.duplicate_mode
+    }
+
+    /// How many steps of parents and children are grabbed along with each post, unless a group overrides it.
+    pub(crate) fn relationship_depth(&self) -> u8 {
+        self.relationship_depth
+    }
+
+    /// Which ratings of posts are grabbed, unless a group overrides it.
+    pub(crate) fn ratings(&self) -> RatingFilter {
+        self.ratings
+    }
+
     /// Checks config and ensure it isn't missing.
     pub(crate) fn config_exists() -> bool {
         if !Path::new(CONFIG_NAME).exists() {
@@ -80,27 +970,78 @@ impl Config {
         CONFIG.get().expect("Config has not been initialized!")
     }
 
-    /// Initializes the global `Config` instance.
+    /// Initializes the global `Config` instance, with the options passed through the command line taking priority.
     pub(crate) fn initialize() -> Result<(), Error> {
-        let config = Self::load_config()?;
+        let mut config = Self::load_config()?;
+        config.apply_arguments(Arguments::get())?;
         CONFIG
             .set(config)
             .map_err(|_| anyhow::anyhow!("Config has already been initialized!"))?;
         Ok(())
     }
 
+    /// Initializes the global `Config` for the tests, which replay every request from the recorded fixtures.
+    #[cfg(test)]
+    pub(crate) fn initialize_for_tests() {
+        CONFIG.get_or_init(|| Config {
+            cassette: CassetteConfig {
+                mode: CassetteMode::Replay,
+                directory: String::from("tests/fixtures/cassette"),
+            },
+            ..Config::default()
+        });
+    }
+
     /// Loads and returns `config` for quick management and settings.
     fn load_config() -> Result<Self, Error> {
         let config_str = read_to_string(CONFIG_NAME)
             .context(format!("Failed to read config file: {CONFIG_NAME}"))?;
-        let config: Config =
+        let mut config: Config =
             from_str(&config_str).context(format!("Failed to parse config file: {CONFIG_NAME}"))?;
         if config.naming_convention.is_empty() {
             return Err(anyhow::anyhow!("Naming convention cannot be empty!"));
         }
 
+        if config.max_concurrent_downloads == 0 {
+            return Err(anyhow::anyhow!(
+                "Max concurrent downloads must be at least 1!"
+            ));
+        }
+
+        config.api_rate_limit.validate("apiRateLimit")?;
+        config.download_rate_limit.validate("downloadRateLimit")?;
+        if config.retry_policy.max_attempts == 0 {
+            return Err(anyhow::anyhow!("retry.maxAttempts must be at least 1!"));
+        }
+
+        config.api_base_url = validate_base_url(&config.api_base_url, "apiBaseUrl")?;
+
+        if let Some(proxy) = &config.proxy {
+            proxy.validate()?;
+        }
+
+        config.bandwidth.validate()?;
+        config.timeouts.validate()?;
+
         Ok(config)
     }
+
+    /// Overrides the options in the config with the ones passed through the command line.
+    ///
+    /// # Arguments
+    ///
+    /// * `arguments`: The command line arguments.
+    fn apply_arguments(&mut self, arguments: &Arguments) -> Result<(), Error> {
+        if let Some(base_url) = arguments.base_url() {
+            self.api_base_url = validate_base_url(base_url, "--base-url")?;
+        }
+
+        if let Some(ratings) = arguments.ratings() {
+            self.ratings = ratings.parse().context("Invalid value for `--ratings`")?;
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for Config {
@@ -109,10 +1050,78 @@ impl Default for Config {
         Config {
             download_directory: String::from("downloads/"),
             naming_convention: String::from("md5"),
+            max_concurrent_downloads: default_max_concurrent_downloads(),
+            api_rate_limit: RateLimit::default_api(),
+            download_rate_limit: RateLimit::default_download(),
+            retry_policy: RetryPolicy::default(),
+            api_base_url: default_api_base_url(),
+            endpoints: HashMap::new(),
+            cassette: CassetteConfig::default(),
+            proxy: None,
+            bandwidth: BandwidthConfig::default(),
+            tag_cache_ttl_hours: default_tag_cache_ttl_hours(),
+            file_variant: FileVariant::default(),
+            timeouts: TimeoutConfig::default(),
+            protocol: ProtocolMode::default(),
+            har_file: None,
+            incremental_sync: false,
+            search_limits: SearchLimits::default(),
+            duplicate_mode: DuplicateMode::default(),
+            relationship_depth: 0,
+            ratings: RatingFilter::default(),
         }
     }
 }
 
+/// Checks that a base url is a valid `http` or `https` url, and returns it without a trailing slash.
+///
+/// # Arguments
+///
+/// * `base_url`: The url to check.
+/// * `name`: The name of the option the url came from.
+///
+/// returns: Result<String, Error>
+fn validate_base_url(base_url: &str, name: &str) -> Result<String, Error> {
+    let url = Url::parse(base_url).with_context(|| format!("{name} is not a valid url!"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow::anyhow!("{name} must be an http or https url!"));
+    }
+
+    Ok(base_url.trim_end_matches('/').to_string())
+}
+
+fn default_api_base_url() -> String {
+    String::from("https://e621.net")
+}
+
+fn default_tag_cache_ttl_hours() -> u64 {
+    24 * 7
+}
+
+fn default_connect_secs() -> u64 {
+    10
+}
+
+fn default_read_idle_secs() -> u64 {
+    30
+}
+
+fn default_request_secs() -> u64 {
+    60
+}
+
+fn default_general_limit() -> PostLimit {
+    PostLimit::Pages(5)
+}
+
+fn default_special_limit() -> PostLimit {
+    PostLimit::All
+}
+
+fn default_max_concurrent_downloads() -> usize {
+    4
+}
+
 fn default_true() -> bool {
     true
 }
@@ -133,6 +1142,22 @@ pub(crate) struct Login {
     /// Whether or not the user wishes to ignore the blacklist when downloading favorites.
     #[serde(rename = "IgnoreBlacklistOnFavorites", default = "default_true")]
     ignore_blacklist_on_favorites: bool,
+    /// What happens when the server refuses the login at startup.
+    #[serde(rename = "OnInvalidLogin", default)]
+    on_invalid_login: InvalidLoginAction,
+}
+
+/// What happens when the server refuses the login at startup.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum InvalidLoginAction {
+    /// The user is asked whether to continue anonymously.
+    #[default]
+    Ask,
+    /// The program continues anonymously without asking.
+    Anonymous,
+    /// The program stops without asking.
+    Exit,
 }
 
 static LOGIN: OnceLock<Login> = OnceLock::new();
@@ -158,6 +1183,11 @@ impl Login {
         self.ignore_blacklist_on_favorites
     }
 
+    /// What happens when the server refuses the login at startup.
+    pub(crate) fn on_invalid_login(&self) -> InvalidLoginAction {
+        self.on_invalid_login
+    }
+
     /// Gets the global instance of [Login].
     pub(crate) fn get() -> &'static Self {
         LOGIN.get().expect("Login has not been initialized!")
@@ -182,6 +1212,17 @@ impl Login {
         Ok(())
     }
 
+    /// Initializes the global `Login` for the tests, with a login that matches the recorded fixtures.
+    #[cfg(test)]
+    pub(crate) fn initialize_for_tests() {
+        LOGIN.get_or_init(|| Login {
+            username: String::from("fixture_user"),
+            api_key: String::from("fixture_api_key"),
+            download_favorites: false,
+            ..Login::default()
+        });
+    }
+
     /// Loads the login file or creates one if it doesn't exist.
     fn load() -> Result<Self, Error> {
         let login_path = Path::new(LOGIN_NAME);
@@ -199,6 +1240,7 @@ impl Login {
             "APIKey",
             "DownloadFavorites",
             "IgnoreBlacklistOnFavorites",
+            "OnInvalidLogin",
         ];
         if expected_keys.iter().any(|key| !content.contains(key)) {
             warn!(
@@ -252,21 +1294,111 @@ impl Default for Login {
             api_key: String::new(),
             download_favorites: true,
             ignore_blacklist_on_favorites: true,
+            on_invalid_login: InvalidLoginAction::default(),
         }
     }
 }
 
-/// Exits the program after message explaining the error and prompting the user to press `ENTER`.
-///
-/// # Arguments
-///
-/// * `error`: The error message to print.
-pub(crate) fn emergency_exit(error: &str) {
-    info!("{error}");
-    println!("Press ENTER to close the application...");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut line = String::new();
-    io::stdin().read_line(&mut line).unwrap_or_default();
+    /// Creates a window from `start` to `end` with no cap.
+    fn window(start: &str, end: &str) -> TransferWindow {
+        TransferWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+            bytes_per_second: None,
+            pause: false,
+        }
+    }
+
+    #[test]
+    fn post_limit_parses_posts_pages_and_all() {
+        assert_eq!("all".parse::<PostLimit>().unwrap(), PostLimit::All);
+        assert_eq!(" ALL ".parse::<PostLimit>().unwrap(), PostLimit::All);
+        assert_eq!("100".parse::<PostLimit>().unwrap(), PostLimit::Posts(100));
+        assert_eq!(
+            "newest 100".parse::<PostLimit>().unwrap(),
+            PostLimit::Posts(100)
+        );
+        assert_eq!("5 pages".parse::<PostLimit>().unwrap(), PostLimit::Pages(5));
+        assert_eq!("5pages".parse::<PostLimit>().unwrap(), PostLimit::Pages(5));
+        assert_eq!("1 page".parse::<PostLimit>().unwrap(), PostLimit::Pages(1));
+    }
+
+    #[test]
+    fn post_limit_rejects_zero_and_invalid_limits() {
+        assert!("0".parse::<PostLimit>().is_err());
+        assert!("0 pages".parse::<PostLimit>().is_err());
+        assert!("some".parse::<PostLimit>().is_err());
+        assert!("-5".parse::<PostLimit>().is_err());
+        assert!("".parse::<PostLimit>().is_err());
+    }
+
+    #[test]
+    fn post_limit_round_trips_through_its_display() {
+        for limit in [
+            PostLimit::All,
+            PostLimit::Posts(320),
+            PostLimit::Pages(1),
+            PostLimit::Pages(5),
+        ] {
+            assert_eq!(limit.to_string().parse::<PostLimit>().unwrap(), limit);
+        }
+    }
+
+    #[test]
+    fn rating_filter_parses_any_combination_of_ratings() {
+        let filter = "sq".parse::<RatingFilter>().unwrap();
+        assert!(filter.allows("s"));
+        assert!(filter.allows("q"));
+        assert!(!filter.allows("e"));
+        assert!(!filter.allows_all());
+        assert_eq!(filter.to_string(), "sq");
 
-    exit(0x00FF);
+        assert_eq!("E, s".parse::<RatingFilter>().unwrap().to_string(), "se");
+        assert!("sqe".parse::<RatingFilter>().unwrap().allows_all());
+    }
+
+    #[test]
+    fn rating_filter_rejects_unknown_and_empty_ratings() {
+        assert!("x".parse::<RatingFilter>().is_err());
+        assert!("safe".parse::<RatingFilter>().is_err());
+        assert!("".parse::<RatingFilter>().is_err());
+        assert!(" , ".parse::<RatingFilter>().is_err());
+    }
+
+    #[test]
+    fn transfer_window_contains_times_between_its_start_and_end() {
+        let window = window("09:00", "17:30");
+        assert!(!window.contains(8 * 60 + 59));
+        assert!(window.contains(9 * 60));
+        assert!(window.contains(12 * 60));
+        assert!(window.contains(17 * 60 + 29));
+        assert!(!window.contains(17 * 60 + 30));
+    }
+
+    #[test]
+    fn transfer_window_runs_past_midnight_when_it_ends_before_it_starts() {
+        let window = window("22:00", "06:00");
+        assert!(window.contains(23 * 60));
+        assert!(window.contains(0));
+        assert!(window.contains(5 * 60 + 59));
+        assert!(!window.contains(6 * 60));
+        assert!(!window.contains(12 * 60));
+    }
+
+    #[test]
+    fn transfer_window_rejects_invalid_times_and_caps() {
+        assert!(window("09:00", "17:30").validate().is_ok());
+        assert!(window("24:00", "17:30").validate().is_err());
+        assert!(window("09:60", "17:30").validate().is_err());
+        assert!(window("9am", "17:30").validate().is_err());
+        assert!(!window("9am", "17:30").contains(10 * 60));
+
+        let mut capped = window("09:00", "17:30");
+        capped.bytes_per_second = Some(0);
+        assert!(capped.validate().is_err());
+    }
 }
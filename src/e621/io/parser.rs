@@ -14,7 +14,7 @@
  * limitations under the License.
  */
 
-use crate::e621::io::emergency_exit;
+use crate::e621::error::E621Error;
 
 /// A parser that's responsible for parsing files character-by-character without any inherit rule.
 ///
@@ -120,21 +120,50 @@ impl BaseParser {
     /// Reports an error to the parser so that it can exit gracefully.
     ///
     /// This will print a message to the console through the `error!` macro.
-    /// After this, it will also attach the current character number and column number to the message.
+    /// After this, it will also attach the current character number and column number to the error returned.
     ///
     /// # Arguments
     ///
     /// * `msg`: Error message to print.
-    pub(crate) fn report_error(&self, msg: &str) {
-        error!(
-            "Error parsing file at character {} (column {}): {msg}",
-            self.pos, self.current_column
-        );
+    ///
+    /// returns: `E621Error`
+    pub(crate) fn report_error(&self, msg: &str) -> E621Error {
+        self.log_error(E621Error::TagSyntax {
+            position: self.pos,
+            column: self.current_column,
+            message: msg.to_string(),
+        })
+    }
+
+    /// Reports an error in the user's blacklist, the same way as [`BaseParser::report_error`].
+    ///
+    /// # Arguments
+    ///
+    /// * `msg`: Error message to print.
+    ///
+    /// returns: `E621Error`
+    pub(crate) fn report_blacklist_error(&self, msg: &str) -> E621Error {
+        self.log_error(E621Error::BlacklistSyntax {
+            position: self.pos,
+            column: self.current_column,
+            message: msg.to_string(),
+        })
+    }
+
+    /// Prints a syntax error to the console before returning it.
+    ///
+    /// # Arguments
+    ///
+    /// * `error`: The error to print.
+    ///
+    /// returns: `E621Error`
+    fn log_error(&self, error: E621Error) -> E621Error {
+        error!("{error}");
         trace!(
             "Total characters: {}, total columns: {}",
             self.total_len, self.total_columns
         );
 
-        emergency_exit("Parser error encountered.");
+        error
     }
 }
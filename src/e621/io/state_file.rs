@@ -0,0 +1,94 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::{read_to_string, write};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{from_str, to_string};
+
+/// Hashes text with FNV-1a, which stays the same between runs and builds unlike the standard library's hasher, so it can
+/// be used in keys that are saved to disk.
+///
+/// # Arguments
+///
+/// * `text`: The text to hash.
+///
+/// returns: String, which is the hash as 16 hex digits.
+pub(crate) fn stable_hash(text: &str) -> String {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+    let hash = text.bytes().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+    format!("{hash:016x}")
+}
+
+/// Loads state saved to disk by an earlier run, starting over with an empty state if the file doesn't exist or can't be
+/// read.
+///
+/// # Arguments
+///
+/// * `path`: The path of the file the state is saved in.
+/// * `name`: What the state is called in the log (e.g "tag cache").
+///
+/// returns: T
+pub(crate) fn load_state<T>(path: &str, name: &str) -> T
+where
+    T: DeserializeOwned + Default,
+{
+    match read_to_string(path) {
+        Ok(contents) => from_str(&contents).unwrap_or_else(|e| {
+            warn!("The {name} is invalid and will be rebuilt: {e}");
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
+}
+
+/// Saves state to disk so the next run can pick it up.
+///
+/// State that can't be saved is only logged, since it can always be built again.
+///
+/// # Arguments
+///
+/// * `path`: The path of the file to save the state in.
+/// * `name`: What the state is called in the log (e.g "tag cache").
+/// * `state`: The state to save.
+///
+/// returns: bool
+pub(crate) fn save_state<T>(path: &str, name: &str, state: &T) -> bool
+where
+    T: Serialize,
+{
+    match to_string(state) {
+        Ok(json) => match write(path, json) {
+            Ok(()) => {
+                trace!("Saved the {name}...");
+                true
+            }
+            Err(e) => {
+                warn!("Unable to save the {name}: {e}");
+                false
+            }
+        },
+        Err(e) => {
+            warn!("Unable to serialize the {name}: {e}");
+            false
+        }
+    }
+}
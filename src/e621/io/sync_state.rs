@@ -0,0 +1,174 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use crate::e621::io::state_file::{load_state, save_state};
+
+/// Constant of the sync state's file name.
+pub(crate) const SYNC_STATE_NAME: &str = "sync_state.json";
+
+/// The newest post downloaded from each search, which is saved to disk between runs so searches can stop early.
+///
+/// Like the tag cache, searches are kept separately for every host (e.g e621 and e6ai). A search is only marked once
+/// every post grabbed from it was downloaded, so posts that failed to download are grabbed again on the next run.
+pub(crate) struct SyncState {
+    /// The highest post ID downloaded from each search, keyed by the url searches are made against and then by the
+    /// search.
+    sections: HashMap<String, HashMap<String, i64>>,
+    /// The url searches are currently made against.
+    source: String,
+    /// Whether the saved IDs are ignored, so every search is walked in full.
+    full_sync: bool,
+    /// Whether the state changed since it was loaded.
+    dirty: bool,
+    /// The path the state is saved to.
+    path: String,
+}
+
+impl SyncState {
+    /// Loads the sync state from disk, starting with an empty state if it doesn't exist or can't be read.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: The url searches are made against.
+    /// * `full_sync`: Whether the saved IDs are ignored, so every search is walked in full.
+    ///
+    /// returns: `SyncState`
+    pub(crate) fn load(source: String, full_sync: bool) -> Self {
+        Self::load_from(SYNC_STATE_NAME, source, full_sync)
+    }
+
+    /// Loads the sync state from the given path, starting with an empty state if it doesn't exist or can't be read.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path the state is saved to.
+    /// * `source`: The url searches are made against.
+    /// * `full_sync`: Whether the saved IDs are ignored, so every search is walked in full.
+    ///
+    /// returns: `SyncState`
+    fn load_from(path: &str, source: String, full_sync: bool) -> Self {
+        let sections = load_state(path, "sync state");
+
+        if full_sync {
+            info!("Walking every search in full, ignoring the sync state...");
+        }
+
+        SyncState {
+            sections,
+            source,
+            full_sync,
+            dirty: false,
+            path: path.to_string(),
+        }
+    }
+
+    /// Gets the highest post ID downloaded from the search in an earlier run, if there is one.
+    ///
+    /// # Arguments
+    ///
+    /// * `search`: The search to look up.
+    ///
+    /// returns: Option<i64>
+    pub(crate) fn last_seen(&self, search: &str) -> Option<i64> {
+        if self.full_sync {
+            return None;
+        }
+
+        self.sections.get(&self.source)?.get(search).copied()
+    }
+
+    /// Marks every post of the search up to the given ID as downloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `search`: The search the posts were grabbed from.
+    /// * `id`: The highest post ID that was downloaded.
+    pub(crate) fn mark(&mut self, search: &str, id: i64) {
+        let last_seen = self
+            .sections
+            .entry(self.source.clone())
+            .or_default()
+            .entry(search.to_string())
+            .or_default();
+        if id > *last_seen {
+            *last_seen = id;
+            self.dirty = true;
+        }
+    }
+
+    /// Saves the sync state to disk if it changed.
+    ///
+    /// A state that can't be saved is only logged, since the searches can always be walked in full again.
+    pub(crate) fn save(&mut self) {
+        if !self.dirty {
+            return;
+        }
+
+        if save_state(&self.path, "sync state", &self.sections) {
+            self.dirty = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+    use std::path::Path;
+    use std::process;
+
+    use super::*;
+
+    /// Gets a path in the temporary directory for a test to save its state to.
+    fn temp_path(name: &str) -> String {
+        let path = temp_dir().join(format!("e621_downloader_{name}_{}.json", process::id()));
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn marks_round_trip_through_the_saved_file() {
+        let path = temp_path("sync_round_trip");
+        let mut state = SyncState::load_from(&path, String::from("https://e621.net"), false);
+        assert_eq!(state.last_seen("set:test_set"), None);
+
+        state.mark("set:test_set", 103);
+        state.mark("set:test_set", 101);
+        state.mark("artist_one", 55);
+        state.save();
+
+        let loaded = SyncState::load_from(&path, String::from("https://e621.net"), false);
+        assert_eq!(loaded.last_seen("set:test_set"), Some(103));
+        assert_eq!(loaded.last_seen("artist_one"), Some(55));
+
+        let other_host = SyncState::load_from(&path, String::from("https://e6ai.net"), false);
+        assert_eq!(other_host.last_seen("set:test_set"), None);
+
+        let full_sync = SyncState::load_from(&path, String::from("https://e621.net"), true);
+        assert_eq!(full_sync.last_seen("set:test_set"), None);
+
+        remove_file(&path).expect("The sync state could not be removed!");
+    }
+
+    #[test]
+    fn unchanged_state_is_not_saved() {
+        let path = temp_path("sync_unchanged");
+        let mut state = SyncState::load_from(&path, String::from("https://e621.net"), false);
+        state.save();
+        assert!(!Path::new(&path).exists());
+    }
+}
@@ -15,17 +15,24 @@
  */
 
 use std::fs::read_to_string;
+use std::path::Path;
+use std::str::FromStr;
 
-use anyhow::{Context, Error};
-
-use crate::e621::io::emergency_exit;
+use crate::e621::error::{E621Error, Result};
+use crate::e621::io::arguments::Arguments;
 use crate::e621::io::parser::BaseParser;
+use crate::e621::io::tag_cache::TagCache;
+use crate::e621::io::{Config, FileVariant, PostLimit, RatingFilter};
 use crate::e621::sender::RequestSender;
-use crate::e621::sender::entries::TagEntry;
+use crate::e621::sender::entries::{AliasEntry, TagEntry};
+use crate::e621::sender::runtime::block_on;
 
 /// Constant of the tag file's name.
 pub(crate) const TAG_NAME: &str = "tags.txt";
 
+/// The maximum amount of names looked up in a single API call.
+const TAG_BATCH_SIZE: usize = 100;
+
 /// An example file for newly created tag files.
 pub(crate) const TAG_FILE_EXAMPLE: &str = include_str!("tags.txt");
 
@@ -60,14 +67,17 @@ pub(crate) struct Tag {
     search_type: TagSearchType,
     /// The tag type of the tag.
     tag_type: TagType,
+    /// How many posts are grabbed from the tag, if its line overrides the group and config.
+    limit: Option<PostLimit>,
 }
 
 impl Tag {
-    fn new(tag: &str, category: TagSearchType, tag_type: TagType) -> Self {
+    pub(crate) fn new(tag: &str, category: TagSearchType, tag_type: TagType) -> Self {
         Tag {
             name: String::from(tag),
             search_type: category,
             tag_type,
+            limit: None,
         }
     }
 
@@ -85,6 +95,11 @@ impl Tag {
     pub(crate) fn tag_type(&self) -> &TagType {
         &self.tag_type
     }
+
+    /// How many posts are grabbed from the tag, if its line overrides the group and config.
+    pub(crate) fn limit(&self) -> Option<PostLimit> {
+        self.limit
+    }
 }
 
 impl Default for Tag {
@@ -93,15 +108,58 @@ impl Default for Tag {
             name: String::new(),
             search_type: TagSearchType::None,
             tag_type: TagType::Unknown,
+            limit: None,
         }
     }
 }
 
+/// Options written in a group's header (e.g `[general variant=sample]`), which override the config for the tags of
+/// that group.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct GroupOptions {
+    /// Which variant of a post's file is downloaded.
+    variant: Option<FileVariant>,
+    /// How many posts are grabbed from each tag.
+    limit: Option<PostLimit>,
+    /// How many steps of parents and children are grabbed along with each post.
+    relationships: Option<u8>,
+    /// Which ratings of posts are grabbed.
+    ratings: Option<RatingFilter>,
+}
+
+impl GroupOptions {
+    /// Which variant of a post's file is downloaded, falling back to the config if the group doesn't set it.
+    pub(crate) fn variant(&self) -> FileVariant {
+        self.variant.unwrap_or_else(|| Config::get().file_variant())
+    }
+
+    /// How many posts are grabbed from each tag, if the group sets it.
+    ///
+    /// This doesn't fall back to the config, since the limit in the config depends on the kind of search.
+    pub(crate) fn limit(&self) -> Option<PostLimit> {
+        self.limit
+    }
+
+    /// How many steps of parents and children are grabbed along with each post, falling back to the config if the
+    /// group doesn't set it.
+    pub(crate) fn relationship_depth(&self) -> u8 {
+        self.relationships
+            .unwrap_or_else(|| Config::get().relationship_depth())
+    }
+
+    /// Which ratings of posts are grabbed, falling back to the config if the group doesn't set it.
+    pub(crate) fn ratings(&self) -> RatingFilter {
+        self.ratings.unwrap_or_else(|| Config::get().ratings())
+    }
+}
+
 /// Group object generated from parsed code.
 #[derive(Debug, Clone)]
 pub(crate) struct Group {
     /// The name of group.
     name: String,
+    /// The options written in the group's header.
+    options: GroupOptions,
     /// A [Vec] containing all the tags parsed.
     tags: Vec<Tag>,
 }
@@ -110,10 +168,16 @@ impl Group {
     pub(crate) fn new(name: String) -> Self {
         Group {
             name,
+            options: GroupOptions::default(),
             tags: Vec::new(),
         }
     }
 
+    /// The options written in the group's header.
+    pub(crate) fn options(&self) -> &GroupOptions {
+        &self.options
+    }
+
     /// The name of group.
     pub(crate) fn name(&self) -> &str {
         &self.name
@@ -131,45 +195,202 @@ impl Group {
 ///
 /// * `request_sender`: The sender to use for the API call (this is used for tag and alias checks).
 ///
-/// returns: Result<Vec<Group, Global>, Error>
-pub(crate) fn parse_tag_file(request_sender: &RequestSender) -> Result<Vec<Group>, Error> {
-    TagParser {
-        parser: BaseParser::new(
-            read_to_string(TAG_NAME)
-                .with_context(|| {
-                    error!("Unable to read tag file!");
-                    "Possible I/O block when trying to read tag file..."
-                })
-                .unwrap(),
-        ),
-        request_sender: request_sender.clone(),
-    }
-    .parse_groups()
+/// returns: Result<Vec<Group, Global>, E621Error>
+pub(crate) fn parse_tag_file(request_sender: &RequestSender) -> Result<Vec<Group>> {
+    let tag_file = read_to_string(TAG_NAME).map_err(|e| {
+        error!("Unable to read tag file!");
+        E621Error::filesystem(Path::new(TAG_NAME), e)
+    })?;
+
+    let mut parser = TagParser {
+        parser: BaseParser::new(tag_file),
+        identifier: TagIdentifier::new(request_sender.clone()),
+    };
+    let result = parser.parse_groups().and_then(|mut groups| {
+        parser.identifier.identify_groups(&mut groups)?;
+        Ok(groups)
+    });
+    parser.identifier.cache.save();
+    result
 }
 
 /// Identifier to help categorize tags.
 pub(crate) struct TagIdentifier {
     /// Request sender for making any needed API calls.
     request_sender: RequestSender,
+    /// Cache of earlier tag and alias lookups, so they aren't made again on every run.
+    cache: TagCache,
 }
 
 impl TagIdentifier {
-    /// Creates new identifier.
+    /// Creates new identifier, loading the tag cache from disk.
     fn new(request_sender: RequestSender) -> Self {
-        TagIdentifier { request_sender }
+        let cache = TagCache::load(
+            request_sender.tag_source(),
+            Config::get().tag_cache_ttl(),
+            Arguments::get().refresh_tags(),
+        );
+        TagIdentifier {
+            request_sender,
+            cache,
+        }
     }
 
-    /// Identifies tags to ensure they exist.
+    /// Identifies every tag of the artists and general groups to ensure they exist.
+    ///
+    /// Every name used by the tags is looked up in batches first, so identifying each tag afterwards doesn't need any
+    /// more API calls. A group with a tag that can't be identified is reported and removed, so the other groups are
+    /// still grabbed.
     ///
     /// # Arguments
     ///
-    /// * `tags`: Tags to id.
-    /// * `request_sender`: The sender to use for the API calls.
+    /// * `groups`: The parsed groups, with their artist and general tags not yet identified.
+    fn identify_groups(&mut self, groups: &mut Vec<Group>) -> Result<()> {
+        let mut names: Vec<&str> = groups
+            .iter()
+            .flat_map(|e| e.tags.iter())
+            .filter(|e| e.tag_type == TagType::Unknown)
+            .flat_map(|e| e.name.split(' '))
+            .map(|e| e.trim_start_matches('-'))
+            .filter(|e| !e.is_empty())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        let names: Vec<String> = names.into_iter().map(String::from).collect();
+        self.prefetch(&names)?;
+
+        groups.retain_mut(|group| match self.identify_group(group) {
+            Ok(()) => true,
+            Err(error) => {
+                warn!(
+                    "Skipping group {}: {error}",
+                    console::style(format!("\"{}\"", group.name))
+                        .color256(39)
+                        .italic()
+                );
+                false
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Identifies every tag of a single group that isn't identified yet.
     ///
-    /// returns: Tag
-    fn id_tag(tags: &str, request_sender: RequestSender) -> Tag {
-        let identifier = TagIdentifier::new(request_sender);
-        identifier.search_for_tag(tags)
+    /// # Arguments
+    ///
+    /// * `group`: The group to identify the tags of.
+    fn identify_group(&mut self, group: &mut Group) -> Result<()> {
+        for tag in group
+            .tags
+            .iter_mut()
+            .filter(|e| e.tag_type == TagType::Unknown)
+        {
+            let limit = tag.limit;
+            *tag = self.search_for_tag(&tag.name)?;
+            tag.limit = limit;
+        }
+
+        Ok(())
+    }
+
+    /// Looks up every name that isn't in the tag cache in batches, along with the aliases of the names that aren't tags.
+    ///
+    /// # Arguments
+    ///
+    /// * `names`: The names to look up.
+    fn prefetch(&mut self, names: &[String]) -> Result<()> {
+        let missing: Vec<&str> = names
+            .iter()
+            .map(String::as_str)
+            .filter(|e| self.cache.cached_tags(e).is_none())
+            .collect();
+        trace!(
+            "{} tags found in tag cache, looking up {} tags...",
+            names.len() - missing.len(),
+            missing.len()
+        );
+        self.fetch_tags(&missing)?;
+
+        let not_found: Vec<&str> = missing
+            .into_iter()
+            .filter(|e| self.cache.cached_tags(e).is_some_and(|e| e.is_empty()))
+            .filter(|e| self.cache.cached_aliases(e).is_none())
+            .collect();
+        self.fetch_aliases(&not_found)?;
+
+        let mut consequents: Vec<String> = not_found
+            .iter()
+            .filter_map(|e| self.cache.cached_aliases(e).flatten())
+            .filter_map(|e| e.first().map(|e| e.consequent_name.clone()))
+            .filter(|e| self.cache.cached_tags(e).is_none())
+            .collect();
+        consequents.sort_unstable();
+        consequents.dedup();
+        let consequents: Vec<&str> = consequents.iter().map(String::as_str).collect();
+        self.fetch_tags(&consequents)
+    }
+
+    /// Looks up the given names in batches and caches the tags found for each of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `names`: The names to look up.
+    fn fetch_tags(&mut self, names: &[&str]) -> Result<()> {
+        for batch in names.chunks(TAG_BATCH_SIZE) {
+            let entries = block_on(self.request_sender.get_tags_by_names(batch))?;
+            for name in batch {
+                let lowercase = name.to_lowercase();
+                let found = entries
+                    .iter()
+                    .filter(|e| e.name == lowercase)
+                    .cloned()
+                    .collect();
+                self.cache.insert_tags(name, found);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Looks up the aliases of the given names in batches and caches the aliases found for each of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `names`: The aliases to look up.
+    fn fetch_aliases(&mut self, names: &[&str]) -> Result<()> {
+        for batch in names.chunks(TAG_BATCH_SIZE) {
+            let entries = block_on(self.request_sender.query_aliases_by_names(batch))?;
+            for name in batch {
+                let lowercase = name.to_lowercase();
+                let found: Vec<AliasEntry> = entries
+                    .iter()
+                    .filter(|e| e.antecedent_name == lowercase)
+                    .cloned()
+                    .collect();
+                self.cache
+                    .insert_aliases(name, (!found.is_empty()).then_some(found));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets tags by their name, from the tag cache if they were looked up before.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag`: The name of the tag.
+    ///
+    /// returns: Result<Vec<`TagEntry`, Global>, E621Error>
+    fn get_tags_by_name(&mut self, tag: &str) -> Result<Vec<TagEntry>> {
+        if let Some(entries) = self.cache.cached_tags(tag) {
+            return Ok(entries);
+        }
+
+        let entries = block_on(self.request_sender.get_tags_by_name(tag))?;
+        self.cache.insert_tags(tag, entries.clone());
+        Ok(entries)
     }
 
     /// Search for tag on e621.
@@ -178,38 +399,32 @@ impl TagIdentifier {
     ///
     /// * `tags`: Tags to search for.
     ///
-    /// returns: Tag
-    fn search_for_tag(&self, tags: &str) -> Tag {
-        // Splits the tags and cycles through each one, checking if they are valid and searchable tags
-        // If the tag isn't searchable, the tag will default and consider itself invalid. Which will
-        // then be filtered through the last step.
-        let mut map = tags
-            .split(' ')
-            .map(|e| {
-                let temp = e.trim_start_matches('-');
-                match self.request_sender.get_tags_by_name(temp).first() {
-                    Some(entry) => self.create_tag(tags, entry),
-                    None => {
-                        if let Some(alias_tag) = self.get_tag_from_alias(temp) {
-                            self.create_tag(tags, &alias_tag)
-                        } else if temp.contains(':') {
-                            Tag::default()
-                        } else {
-                            self.exit_tag_failure(temp);
-                            unreachable!();
-                        }
+    /// returns: Result<Tag, E621Error>
+    fn search_for_tag(&mut self, tags: &str) -> Result<Tag> {
+        // Splits the tags and cycles through each one, checking if they are valid and searchable tags.
+        // The first tag with category special is returned. If no tag is special, the tags are considered general,
+        // which is also the case if every tag is syntax only.
+        for e in tags.split(' ') {
+            let temp = e.trim_start_matches('-');
+            let tag = match self.get_tags_by_name(temp)?.first() {
+                Some(entry) => self.create_tag(tags, entry),
+                None => {
+                    if let Some(alias_tag) = self.get_tag_from_alias(temp)? {
+                        self.create_tag(tags, &alias_tag)
+                    } else if temp.contains(':') {
+                        continue;
+                    } else {
+                        return Err(self.tag_failure(temp));
                     }
                 }
-            })
-            .filter(|e| *e != Tag::default());
+            };
+
+            if tag.search_type == TagSearchType::Special {
+                return Ok(tag);
+            }
+        }
 
-        // Tries to return any tag in the map with category special, return the last element otherwise.
-        // If returning the last element fails, assume the tag is syntax only and default.
-        map.find(|e| e.search_type == TagSearchType::Special)
-            .unwrap_or_else(|| {
-                map.next_back()
-                    .unwrap_or_else(|| Tag::new(tags, TagSearchType::General, TagType::General))
-            })
+        Ok(Tag::new(tags, TagSearchType::General, TagType::General))
     }
 
     /// Checks if the tag is an alias and searches for the tag it is aliased to, returning it.
@@ -218,25 +433,38 @@ impl TagIdentifier {
     ///
     /// * `tag`: Alias to check for.
     ///
-    /// returns: Option<TagEntry>
-    fn get_tag_from_alias(&self, tag: &str) -> Option<TagEntry> {
-        let entry = self.request_sender.query_aliases(tag)?.first()?.clone();
+    /// returns: Result<Option<TagEntry>, E621Error>
+    fn get_tag_from_alias(&mut self, tag: &str) -> Result<Option<TagEntry>> {
+        let aliases = match self.cache.cached_aliases(tag) {
+            Some(aliases) => aliases,
+            None => {
+                let aliases = block_on(self.request_sender.query_aliases(tag))?;
+                self.cache.insert_aliases(tag, aliases.clone());
+                aliases
+            }
+        };
 
-        self.request_sender
-            .get_tags_by_name(&entry.consequent_name)
+        let Some(entry) = aliases.and_then(|e| e.first().cloned()) else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .get_tags_by_name(&entry.consequent_name)?
             .first()
-            .cloned()
+            .cloned())
     }
 
-    /// Emergency exits if a tag isn't identified.
+    /// Logs and creates the error for a tag that isn't identified.
     ///
     /// # Arguments
     ///
     /// * `tag`: Tag to log for.
-    fn exit_tag_failure(&self, tag: &str) {
+    ///
+    /// returns: `E621Error`
+    fn tag_failure(&self, tag: &str) -> E621Error {
         error!("{tag} is invalid!");
         info!("The tag may be a typo, be sure to double check and ensure that the tag is correct.");
-        emergency_exit(format!("The server API call was unable to find tag: {tag}!").as_str());
+        E621Error::UnknownTag(tag.to_string())
     }
 
     /// Processes the tag type and creates the appropriate tag for it.
@@ -274,13 +502,13 @@ impl TagIdentifier {
 struct TagParser {
     /// Low-level parser for parsing raw data.
     parser: BaseParser,
-    /// Request sender for any needed API calls.
-    request_sender: RequestSender,
+    /// Identifier for the tags of the artists and general groups.
+    identifier: TagIdentifier,
 }
 
 impl TagParser {
     /// Parses each group with all tags tied to them before returning a vector with all groups in it.
-    pub(crate) fn parse_groups(&mut self) -> Result<Vec<Group>, Error> {
+    pub(crate) fn parse_groups(&mut self) -> Result<Vec<Group>> {
         let mut groups: Vec<Group> = Vec::new();
         loop {
             self.parser.consume_whitespace();
@@ -293,9 +521,9 @@ impl TagParser {
             }
 
             if self.parser.starts_with("[") {
-                groups.push(self.parse_group());
+                groups.push(self.parse_group()?);
             } else {
-                self.parser.report_error("Tags must be in groups!");
+                return Err(self.parser.report_error("Tags must be in groups!"));
             }
         }
 
@@ -303,15 +531,113 @@ impl TagParser {
     }
 
     /// Parses a group and all tags tied to it before returning the result.
-    fn parse_group(&mut self) -> Group {
+    fn parse_group(&mut self) -> Result<Group> {
         assert_eq!(self.parser.consume_char(), '[');
         let group_name = self.parser.consume_while(valid_group);
-        assert_eq!(self.parser.consume_char(), ']');
-
         let mut group = Group::new(group_name);
-        self.parse_tags(&mut group);
+        self.parse_group_options(&mut group.options)?;
+        if self.parser.eof() || self.parser.consume_char() != ']' {
+            return Err(self.parser.report_error("Group names must end with `]`!"));
+        }
+
+        self.parse_tags(&mut group)?;
+
+        Ok(group)
+    }
+
+    /// Parses the `key=value` options that follow the name of a group, up to the closing `]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options of the group to fill.
+    fn parse_group_options(&mut self, options: &mut GroupOptions) -> Result<()> {
+        while let Some((key, value)) = self.parse_option()? {
+            match key.as_str() {
+                "variant" => options.variant = Some(self.parse_option_value(&value)?),
+                "limit" => options.limit = Some(self.parse_option_value(&value)?),
+                "ratings" => options.ratings = Some(self.parse_option_value(&value)?),
+                "relationships" => {
+                    options.relationships = Some(value.parse().map_err(|_| {
+                        self.parser.report_error(&format!(
+                            "\"{value}\" is not a relationship depth, expected a number from 0 to 255!"
+                        ))
+                    })?);
+                }
+                _ => {
+                    return Err(self
+                        .parser
+                        .report_error(&format!("Unknown group option `{key}`!")));
+                }
+            }
+        }
 
-        group
+        Ok(())
+    }
+
+    /// Parses the `[key=value]` options at the end of a tag's line, if there are any.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag`: The tag the options are for.
+    fn parse_tag_options(&mut self, tag: &mut Tag) -> Result<()> {
+        self.parser.consume_while(|c| c == ' ' || c == '\t');
+        if !self.parser.starts_with("[") {
+            return Ok(());
+        }
+
+        self.parser.consume_char();
+        while let Some((key, value)) = self.parse_option()? {
+            match key.as_str() {
+                "limit" => tag.limit = Some(self.parse_option_value(&value)?),
+                _ => {
+                    return Err(self
+                        .parser
+                        .report_error(&format!("Unknown tag option `{key}`!")));
+                }
+            }
+        }
+
+        if self.parser.eof() || self.parser.consume_char() != ']' {
+            return Err(self.parser.report_error("Tag options must end with `]`!"));
+        }
+
+        Ok(())
+    }
+
+    /// Parses the next `key=value` option, or returns `None` once the closing `]` is reached.
+    ///
+    /// returns: Result<Option<(String, String)>, E621Error>
+    fn parse_option(&mut self) -> Result<Option<(String, String)>> {
+        self.parser.consume_while(|c| c == ' ' || c == '\t');
+        if self.parser.eof() || self.parser.starts_with("]") {
+            return Ok(None);
+        }
+
+        let key = self.parser.consume_while(valid_group);
+        if key.is_empty() || self.parser.eof() || self.parser.consume_char() != '=' {
+            return Err(self
+                .parser
+                .report_error("Options must be written as `key=value`!"));
+        }
+
+        let value = self.parser.consume_while(valid_option_value);
+        Ok(Some((key, value)))
+    }
+
+    /// Parses the value of an option, reporting where it is if it's invalid.
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: The value to parse.
+    ///
+    /// returns: Result<T, E621Error>
+    fn parse_option_value<T>(&self, value: &str) -> Result<T>
+    where
+        T: FromStr<Err = anyhow::Error>,
+    {
+        value
+            .parse()
+            .map_err(|e: anyhow::Error| self.parser.report_error(&e.to_string()))
     }
 
     /// Parses all tags for a group and stores it.
@@ -319,7 +645,7 @@ impl TagParser {
     /// # Arguments
     ///
     /// * `group`: The group to parse.
-    fn parse_tags(&mut self, group: &mut Group) {
+    fn parse_tags(&mut self, group: &mut Group) -> Result<()> {
         let mut tags: Vec<Tag> = Vec::new();
         loop {
             self.parser.consume_whitespace();
@@ -335,44 +661,49 @@ impl TagParser {
                 break;
             }
 
-            tags.push(self.parse_tag(group.name()));
+            tags.push(self.parse_tag(group.name())?);
         }
 
         group.tags = tags;
+        Ok(())
     }
 
-    /// Parses a single tag and identifies it before returning the result.
+    /// Parses a single tag before returning the result. Pools, sets, and posts are identified right away.
     ///
     /// # Arguments
     ///
     /// * `group_name`:  Group name to parse the tag for.
     ///
-    /// returns: Tag
-    fn parse_tag(&mut self, group_name: &str) -> Tag {
+    /// returns: Result<Tag, E621Error>
+    fn parse_tag(&mut self, group_name: &str) -> Result<Tag> {
         match group_name {
             "artists" | "general" => {
+                // Artist and general tags are identified once every group is parsed, so their names can be looked up
+                // in batches.
                 let tag = self.parser.consume_while(valid_tag);
-                TagIdentifier::id_tag(tag.trim(), self.request_sender.clone())
+                let mut tag = Tag::new(tag.trim(), TagSearchType::None, TagType::Unknown);
+                self.parse_tag_options(&mut tag)?;
+                Ok(tag)
             }
             e => {
                 let temp_char = self.parser.next_char();
-                assert!(
-                    char::is_ascii_digit(&temp_char) || temp_char == '#',
-                    "Invalid tag type! Pools, sets, and single-post tags must be a number!"
-                );
+                if !char::is_ascii_digit(&temp_char) && temp_char != '#' {
+                    return Err(self.parser.report_error(
+                        "Invalid tag type! Pools, sets, and single-post tags must be a number!",
+                    ));
+                }
 
                 let tag = self.parser.consume_while(valid_id);
                 let tag_type = match e {
                     "pools" => TagType::Pool,
                     "sets" => TagType::Set,
                     "single-post" => TagType::Post,
-                    _ => {
-                        self.parser.report_error("Unknown tag type!");
-                        TagType::Unknown
-                    }
+                    _ => return Err(self.parser.report_error("Unknown tag type!")),
                 };
 
-                Tag::new(tag.trim(), TagSearchType::Special, tag_type)
+                let mut tag = Tag::new(tag.trim(), TagSearchType::Special, tag_type);
+                self.parse_tag_options(&mut tag)?;
+                Ok(tag)
             }
         }
     }
@@ -402,6 +733,8 @@ impl TagParser {
 /// returns: bool
 fn valid_tag(c: char) -> bool {
     match c {
+        // `[` starts the options of the tag's line.
+        '[' => false,
         ' '..='\"' | '$'..='~' => true,
         // This will check for any special characters in the validator.
         _ => {
@@ -436,6 +769,17 @@ fn valid_group(c: char) -> bool {
     matches!(c, 'A'..='Z' | 'a'..='z' | '-')
 }
 
+/// Validates character for the value of a group option.
+///
+/// # Arguments
+///
+/// * `c`: The character to check.
+///
+/// returns: bool
+fn valid_option_value(c: char) -> bool {
+    !c.is_whitespace() && c != ']' && c != '#'
+}
+
 /// Validates character for comment.
 ///
 /// # Arguments
@@ -449,3 +793,78 @@ fn valid_comment(c: char) -> bool {
         _ => c.is_alphanumeric(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::e621::io::Login;
+
+    /// Parses the groups of a tag file, without identifying its artist and general tags.
+    fn parse_groups(tag_file: &str) -> Result<Vec<Group>> {
+        Config::initialize_for_tests();
+        Login::initialize_for_tests();
+        let request_sender =
+            RequestSender::new().expect("The request sender could not be created!");
+        let mut parser = TagParser {
+            parser: BaseParser::new(tag_file.to_string()),
+            identifier: TagIdentifier {
+                cache: TagCache::load(request_sender.tag_source(), None, false),
+                request_sender,
+            },
+        };
+        parser.parse_groups()
+    }
+
+    #[test]
+    fn parses_group_options() {
+        let groups = parse_groups(
+            "[general variant=sample limit=5pages ratings=sq relationships=2]\nfluffy\n[artists]\nsome_artist\n",
+        )
+        .expect("The tag file could not be parsed!");
+        assert_eq!(groups.len(), 2);
+
+        let options = groups[0].options();
+        assert_eq!(options.variant(), FileVariant::Sample);
+        assert_eq!(options.limit(), Some(PostLimit::Pages(5)));
+        assert_eq!(options.ratings().to_string(), "sq");
+        assert_eq!(options.relationship_depth(), 2);
+
+        let options = groups[1].options();
+        assert_eq!(options.variant(), Config::get().file_variant());
+        assert_eq!(options.limit(), None);
+    }
+
+    #[test]
+    fn parses_tag_options() {
+        let groups = parse_groups(
+            "[artists]\nsome_artist [limit=newest100]\nother_artist\n[pools]\n1234 [limit=all]\n",
+        )
+        .expect("The tag file could not be parsed!");
+        let artists = groups[0].tags();
+        assert_eq!(artists[0].name(), "some_artist");
+        assert_eq!(artists[0].limit(), Some(PostLimit::Posts(100)));
+        assert_eq!(artists[1].name(), "other_artist");
+        assert_eq!(artists[1].limit(), None);
+
+        let pool = &groups[1].tags()[0];
+        assert_eq!(pool.name(), "1234");
+        assert_eq!(pool.tag_type(), &TagType::Pool);
+        assert_eq!(pool.limit(), Some(PostLimit::All));
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        assert!(parse_groups("[general size=big]\nfluffy\n").is_err());
+        assert!(parse_groups("[general limit=0]\nfluffy\n").is_err());
+        assert!(parse_groups("[general ratings=x]\nfluffy\n").is_err());
+        assert!(parse_groups("[general relationships=300]\nfluffy\n").is_err());
+        assert!(parse_groups("[general variant]\nfluffy\n").is_err());
+        assert!(parse_groups("[artists]\nsome_artist [order=new]\n").is_err());
+        assert!(parse_groups("[artists]\nsome_artist [limit=5\n").is_err());
+    }
+
+    #[test]
+    fn parses_the_example_tag_file() {
+        parse_groups(TAG_FILE_EXAMPLE).expect("The example tag file could not be parsed!");
+    }
+}
@@ -0,0 +1,205 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::e621::io::state_file::{load_state, save_state};
+use crate::e621::sender::entries::{AliasEntry, TagEntry};
+
+/// Constant of the tag cache's file name.
+pub(crate) const TAG_CACHE_NAME: &str = "tag_cache.json";
+
+/// A lookup result saved in the cache, along with when it was saved.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CachedEntry<T> {
+    /// When the result was saved, in seconds since the unix epoch.
+    cached_at: u64,
+    /// The result of the lookup.
+    value: T,
+}
+
+/// The cached lookups made against a single host.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct CacheSection {
+    /// Tag lookups keyed by the tag name.
+    tags: HashMap<String, CachedEntry<Vec<TagEntry>>>,
+    /// Alias lookups keyed by the alias name.
+    aliases: HashMap<String, CachedEntry<Option<Vec<AliasEntry>>>>,
+}
+
+/// A cache of tag and alias lookups that is saved to disk between runs.
+///
+/// Lookups are kept separately for every host (e.g e621 and e6ai), since the post count of a tag differs between them.
+/// A lookup older than the TTL is made again, and with `--refresh-tags` every lookup is made again. Lookups are kept in
+/// memory for the rest of the run even when the cache is disabled, and are only saved to disk when it is enabled.
+pub(crate) struct TagCache {
+    /// Every cached lookup, keyed by the url the lookups were made against.
+    sections: HashMap<String, CacheSection>,
+    /// The url lookups are currently made against.
+    source: String,
+    /// How long a lookup stays valid, or `None` if the cache is disabled.
+    ttl: Option<Duration>,
+    /// Whether every cached lookup is ignored and made again.
+    refresh: bool,
+    /// Whether the cache changed since it was loaded.
+    dirty: bool,
+    /// When the cache was loaded, in seconds since the unix epoch.
+    started_at: u64,
+}
+
+impl TagCache {
+    /// Loads the cache from disk, starting with an empty cache if it doesn't exist or can't be read.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: The url lookups are made against.
+    /// * `ttl`: How long a lookup stays valid, or `None` to disable the cache.
+    /// * `refresh`: Whether every cached lookup is ignored and made again.
+    ///
+    /// returns: `TagCache`
+    pub(crate) fn load(source: String, ttl: Option<Duration>, refresh: bool) -> Self {
+        let sections = ttl
+            .map(|_| load_state(TAG_CACHE_NAME, "tag cache"))
+            .unwrap_or_default();
+
+        if refresh {
+            info!("Refreshing every tag in the tag cache...");
+        }
+
+        TagCache {
+            sections,
+            source,
+            ttl,
+            refresh,
+            dirty: false,
+            started_at: Self::now(),
+        }
+    }
+
+    /// The current time in seconds since the unix epoch.
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |e| e.as_secs())
+    }
+
+    /// Checks whether a cached lookup can still be used.
+    ///
+    /// Lookups made during this run are always used, even if the cache is disabled or being refreshed.
+    ///
+    /// # Arguments
+    ///
+    /// * `cached_at`: When the lookup was saved, in seconds since the unix epoch.
+    ///
+    /// returns: bool
+    fn is_fresh(&self, cached_at: u64) -> bool {
+        cached_at >= self.started_at
+            || (!self.refresh
+                && self
+                    .ttl
+                    .is_some_and(|ttl| Self::now().saturating_sub(cached_at) < ttl.as_secs()))
+    }
+
+    /// Gets the cached lookup of the tags with the given name, if it is still fresh.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the tag.
+    ///
+    /// returns: Option<Vec<`TagEntry`, Global>>
+    pub(crate) fn cached_tags(&self, name: &str) -> Option<Vec<TagEntry>> {
+        let entry = self.sections.get(&self.source)?.tags.get(name)?;
+        self.is_fresh(entry.cached_at).then(|| entry.value.clone())
+    }
+
+    /// Caches the lookup of the tags with the given name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the tag.
+    /// * `value`: The tags that were found.
+    pub(crate) fn insert_tags(&mut self, name: &str, value: Vec<TagEntry>) {
+        let entry = self.new_entry(value);
+        self.section_mut().tags.insert(name.to_string(), entry);
+    }
+
+    /// Gets the cached lookup of the aliases of the given name, if it is still fresh.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The alias to look up.
+    ///
+    /// returns: Option<Option<Vec<`AliasEntry`, Global>>>
+    pub(crate) fn cached_aliases(&self, name: &str) -> Option<Option<Vec<AliasEntry>>> {
+        let entry = self.sections.get(&self.source)?.aliases.get(name)?;
+        self.is_fresh(entry.cached_at).then(|| entry.value.clone())
+    }
+
+    /// Caches the lookup of the aliases of the given name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The alias that was looked up.
+    /// * `value`: The aliases that were found.
+    pub(crate) fn insert_aliases(&mut self, name: &str, value: Option<Vec<AliasEntry>>) {
+        let entry = self.new_entry(value);
+        self.section_mut().aliases.insert(name.to_string(), entry);
+    }
+
+    /// Saves the cache to disk if it changed, dropping every lookup that expired.
+    ///
+    /// A cache that can't be saved is only logged, since the lookups can always be made again.
+    pub(crate) fn save(&mut self) {
+        let Some(ttl) = self.ttl.filter(|_| self.dirty) else {
+            return;
+        };
+
+        let oldest = Self::now().saturating_sub(ttl.as_secs());
+        for section in self.sections.values_mut() {
+            section.tags.retain(|_, e| e.cached_at >= oldest);
+            section.aliases.retain(|_, e| e.cached_at >= oldest);
+        }
+        self.sections
+            .retain(|_, e| !e.tags.is_empty() || !e.aliases.is_empty());
+
+        if save_state(TAG_CACHE_NAME, "tag cache", &self.sections) {
+            self.dirty = false;
+        }
+    }
+
+    /// Creates a cache entry saved at the current time, marking the cache as changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: The result of the lookup.
+    ///
+    /// returns: CachedEntry<T>
+    fn new_entry<T>(&mut self, value: T) -> CachedEntry<T> {
+        self.dirty = true;
+        CachedEntry {
+            cached_at: Self::now(),
+            value,
+        }
+    }
+
+    /// The cached lookups for the current host, creating them if there are none.
+    fn section_mut(&mut self) -> &mut CacheSection {
+        self.sections.entry(self.source.clone()).or_default()
+    }
+}
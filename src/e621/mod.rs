@@ -14,30 +14,544 @@
  * limitations under the License.
  */
 
-use std::cell::RefCell;
-use std::fs::{create_dir_all, write};
-use std::path::PathBuf;
-use std::rc::Rc;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf, absolute};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 
-use anyhow::{Context, Error};
-use dialoguer::Confirm;
+use anyhow::{Context, Error, anyhow};
 use indicatif::{ProgressBar, ProgressDrawTarget};
+use tokio::fs::{copy, create_dir_all, hard_link, try_exists};
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use tokio::sync::{Semaphore, watch};
+use tokio::task::{JoinError, JoinSet};
 
 use crate::e621::blacklist::Blacklist;
-use crate::e621::grabber::{Grabber, Shorten};
+use crate::e621::error::{E621Error, Result as E621Result};
+use crate::e621::grabber::{GrabbedPost, Grabber, PostCollection, Shorten};
+use crate::e621::io::arguments::Arguments;
+use crate::e621::io::sync_state::SyncState;
 use crate::e621::io::tag::Group;
-use crate::e621::io::{Config, Login};
+use crate::e621::io::{Config, DuplicateMode, FileVariant, Login};
 use crate::e621::sender::RequestSender;
 use crate::e621::sender::entries::UserEntry;
+use crate::e621::sender::runtime::{block_on, runtime};
 use crate::e621::tui::{ProgressBarBuilder, ProgressStyleBuilder};
 
 pub(crate) mod blacklist;
+pub(crate) mod error;
 pub(crate) mod grabber;
 pub(crate) mod io;
 pub(crate) mod sender;
 pub(crate) mod tui;
 
+/// A post that is queued to be downloaded by one of the download tasks.
+struct DownloadJob {
+    /// The post to download.
+    post: GrabbedPost,
+    /// The path the post will be saved to.
+    file_path: PathBuf,
+    /// The shortened name of the collection the post belongs to.
+    collection_name: String,
+    /// The sync progress of the collection the post belongs to, if its search is tracked in the sync state.
+    collection_sync: Option<Arc<CollectionSync>>,
+    /// Whether the post is downloaded, or linked to where an earlier job downloaded it.
+    source: JobSource,
+}
+
+/// Where a queued post comes from.
+enum JobSource {
+    /// The post is downloaded, and whether it was downloaded is sent to the jobs of its duplicates.
+    Download(Option<watch::Sender<Option<bool>>>),
+    /// The post was already queued by another collection, so it's linked to that download once it finishes.
+    Duplicate(SharedDownload),
+}
+
+/// A post that was queued to be downloaded, which later jobs of the same post wait on instead of downloading it again.
+#[derive(Clone)]
+struct SharedDownload {
+    /// The path the post is downloaded to.
+    file_path: PathBuf,
+    /// Whether the post was downloaded, which is `None` until the download finishes.
+    finished: watch::Receiver<Option<bool>>,
+}
+
+/// The download progress of a collection whose search is tracked in the sync state, shared by the jobs of its posts.
+struct CollectionSync {
+    /// The search the collection came from.
+    search: String,
+    /// The newest post ID in the collection.
+    newest_id: i64,
+    /// How many posts of the collection are still downloading.
+    remaining: AtomicUsize,
+    /// Whether any post of the collection failed to download.
+    failed: AtomicBool,
+}
+
+/// A post that could not be downloaded, which is reported at the end of the run.
+struct DownloadFailure {
+    /// The path the post was going to be saved to.
+    file_path: PathBuf,
+    /// Why the post could not be downloaded.
+    error: E621Error,
+}
+
+/// Downloads the posts of every grabbed collection, with each post downloaded in its own task.
+///
+/// The downloader is cheap to clone, so every download task can own one.
+#[derive(Clone)]
+struct Downloader {
+    /// The sender used for all downloads.
+    request_sender: RequestSender,
+    /// The location of the download directory.
+    download_directory: String,
+    /// Progress bar that displays the current progress in downloading posts.
+    progress_bar: ProgressBar,
+    /// The newest post downloaded from each search, if incremental sync is enabled.
+    sync_state: Option<Arc<Mutex<SyncState>>>,
+}
+
+impl Downloader {
+    /// Removes invalid characters from directory path.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir_name`: Directory name to remove invalid chars from.
+    ///
+    /// returns: String
+    fn remove_invalid_chars(&self, dir_name: &str) -> String {
+        dir_name
+            .chars()
+            .map(|e| match e {
+                '?' | ':' | '*' | '<' | '>' | '\"' | '|' | '/' | '\\' => '_',
+                _ => e,
+            })
+            .collect()
+    }
+
+    /// Processes a `PostCollection` and queues all of its posts for the download tasks.
+    ///
+    /// # Arguments
+    ///
+    /// * `collection`: The collection to queue.
+    ///
+    /// returns: Result<Vec<`DownloadJob`, Global>, Error>
+    fn collect_download_jobs(
+        &self,
+        collection: &PostCollection,
+    ) -> Result<Vec<DownloadJob>, Error> {
+        let collection_name = collection.name();
+        let collection_category = collection.category();
+        let collection_posts = collection.posts();
+        let collection_count = collection_posts.len();
+        let short_collection_name = collection.shorten("...");
+
+        #[cfg(unix)]
+        let static_path: PathBuf = [
+            &self.download_directory,
+            collection.category(),
+            &self.remove_invalid_chars(collection_name),
+        ]
+        .iter()
+        .collect();
+
+        #[cfg(windows)]
+        let mut static_path: PathBuf = [
+            &self.download_directory,
+            collection.category(),
+            &self.remove_invalid_chars(collection_name),
+        ]
+        .iter()
+        .collect();
+
+        // This is put here to attempt to shorten the length of the path if it passes window's
+        // max path length.
+        #[cfg(windows)]
+        const MAX_PATH: usize = 260; // Defined in Windows documentation.
+
+        #[cfg(windows)]
+        let start_path_len = static_path.as_os_str().len();
+
+        #[cfg(windows)]
+        if start_path_len >= MAX_PATH {
+            static_path = [
+                &self.download_directory,
+                collection_category,
+                &self.remove_invalid_chars(&collection.shorten('_')),
+            ]
+            .iter()
+            .collect();
+
+            let new_len = static_path.as_os_str().len();
+            if new_len >= MAX_PATH {
+                error!(
+                    "Path is too long and crosses the {MAX_PATH} char limit.\
+                   Please relocate the program to a directory closer to the root drive directory."
+                );
+                trace!("Path length: {new_len}");
+            }
+        }
+
+        trace!("Printing Collection Info:");
+        trace!("Collection Name:            \"{collection_name}\"");
+        trace!("Collection Category:        \"{collection_category}\"");
+        trace!("Collection Post Length:     \"{collection_count}\"");
+        trace!(
+            "Static file path for this collection: \"{}\"",
+            static_path.to_string_lossy()
+        );
+
+        let static_path_str = static_path
+            .to_str()
+            .context("Path contains invalid UTF-8")?;
+        let collection_sync =
+            self.sync_state
+                .as_ref()
+                .and(collection.sync_mark())
+                .map(|(search, newest_id)| {
+                    Arc::new(CollectionSync {
+                        search: search.clone(),
+                        newest_id: *newest_id,
+                        remaining: AtomicUsize::new(collection_count),
+                        failed: AtomicBool::new(false),
+                    })
+                });
+        Ok(collection_posts
+            .iter()
+            .map(|post| DownloadJob {
+                post: post.clone(),
+                file_path: [static_path_str, &self.remove_invalid_chars(post.name())]
+                    .iter()
+                    .collect(),
+                collection_name: short_collection_name.clone(),
+                collection_sync: collection_sync.clone(),
+                source: JobSource::Download(None),
+            })
+            .collect())
+    }
+
+    /// Downloads a single queued post, skipping it if it already exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `job`: The job to download.
+    async fn download_job(&self, job: &DownloadJob) -> E621Result<()> {
+        let DownloadJob {
+            post,
+            file_path,
+            collection_name,
+            ..
+        } = job;
+
+        if try_exists(file_path).await.unwrap_or(false) {
+            self.progress_bar
+                .set_message("Duplicate found: skipping... ");
+            if let Some(file_size) = post.file_size() {
+                self.progress_bar.inc(file_size.cast_unsigned());
+            }
+            return Ok(());
+        }
+
+        self.progress_bar
+            .set_message(format!("Downloading: {collection_name} "));
+
+        if let Some(parent_path) = file_path.parent() {
+            create_dir_all(parent_path).await.map_err(|e| {
+                error!("Could not create directories for images!");
+                E621Error::filesystem(parent_path, e)
+            })?;
+        }
+
+        self.request_sender
+            .download_image(
+                post.url(),
+                post.md5(),
+                post.file_size().map(i64::cast_unsigned),
+                file_path,
+                &self.progress_bar,
+            )
+            .await
+    }
+
+    /// Runs a queued job, either downloading its post or linking it to where a duplicate was downloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `job`: The job to run.
+    /// * `permits`: The permits limiting how many downloads run at the same time, which a duplicate takes if it has to
+    ///   be downloaded after all.
+    async fn run_job(&self, job: &DownloadJob, permits: &Arc<Semaphore>) -> E621Result<()> {
+        match &job.source {
+            JobSource::Download(finished) => {
+                let result = self.download_job(job).await;
+                if let Some(finished) = finished {
+                    finished.send_replace(Some(result.is_ok()));
+                }
+
+                result
+            }
+            JobSource::Duplicate(original) => {
+                let mut finished = original.finished.clone();
+                let downloaded = finished
+                    .wait_for(Option::is_some)
+                    .await
+                    .is_ok_and(|e| *e == Some(true));
+                if downloaded {
+                    return self.link_job(job, &original.file_path).await;
+                }
+
+                // The first copy couldn't be downloaded, so this one is downloaded on its own. Its size was already
+                // added to the progress bar when the first copy was queued, so it isn't added again.
+                let _permit = Arc::clone(permits)
+                    .acquire_owned()
+                    .await
+                    .expect("Download permits are never closed!");
+                self.download_job(job).await
+            }
+        }
+    }
+
+    /// Saves a duplicate post by linking or copying the file downloaded for it, depending on the `duplicateMode` option
+    /// in the config.
+    ///
+    /// A hard link that can't be created (e.g the locations are on different drives) falls back to a copy.
+    ///
+    /// # Arguments
+    ///
+    /// * `job`: The job of the duplicate.
+    /// * `original`: The path the post was downloaded to.
+    async fn link_job(&self, job: &DownloadJob, original: &Path) -> E621Result<()> {
+        let file_path = &job.file_path;
+        if file_path == original || try_exists(file_path).await.unwrap_or(false) {
+            return Ok(());
+        }
+
+        self.progress_bar
+            .set_message(format!("Linking: {} ", job.collection_name));
+
+        if let Some(parent_path) = file_path.parent() {
+            create_dir_all(parent_path).await.map_err(|e| {
+                error!("Could not create directories for images!");
+                E621Error::filesystem(parent_path, e)
+            })?;
+        }
+
+        trace!(
+            "Linking \"{}\" to \"{}\"...",
+            file_path.to_string_lossy(),
+            original.to_string_lossy()
+        );
+        let result = match Config::get().duplicate_mode() {
+            DuplicateMode::Hardlink => match hard_link(original, file_path).await {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    trace!("Unable to create hard link, copying instead: {e}");
+                    copy(original, file_path).await.map(|_| ())
+                }
+            },
+            DuplicateMode::Symlink => {
+                // Symbolic links are relative to the link's directory, so the target is made absolute.
+                let target = absolute(original).unwrap_or_else(|_| original.to_path_buf());
+
+                #[cfg(unix)]
+                let result = tokio::fs::symlink(target, file_path).await;
+
+                #[cfg(windows)]
+                let result = tokio::fs::symlink_file(target, file_path).await;
+
+                result
+            }
+            DuplicateMode::Copy => copy(original, file_path).await.map(|_| ()),
+        };
+
+        result.map_err(|e| {
+            error!("Could not save duplicate post!");
+            E621Error::filesystem(file_path, e)
+        })
+    }
+
+    /// Finds the jobs whose post was already queued by an earlier job, which are turned into duplicates of that job
+    /// instead of being downloaded again.
+    ///
+    /// Posts are matched by the md5 of their original file along with the variant that is downloaded, so a sample or
+    /// preview is only matched with the same variant of the same post. Posts the server doesn't list an md5 for are
+    /// always downloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `jobs`: The jobs of a collection.
+    /// * `downloads`: Every post queued so far during the run, keyed by md5 and variant.
+    ///
+    /// returns: usize, with the amount of duplicates found.
+    fn deduplicate_jobs(
+        jobs: &mut [DownloadJob],
+        downloads: &mut HashMap<(String, FileVariant), SharedDownload>,
+    ) -> usize {
+        let mut duplicates = 0;
+        for job in jobs {
+            let Some((md5, variant)) = job.post.file_key() else {
+                continue;
+            };
+
+            let key = (md5.to_string(), variant);
+            match downloads.get(&key) {
+                Some(original) => {
+                    job.source = JobSource::Duplicate(original.clone());
+                    duplicates += 1;
+                }
+                None => {
+                    let (finished, receiver) = watch::channel(None);
+                    downloads.insert(
+                        key,
+                        SharedDownload {
+                            file_path: job.file_path.clone(),
+                            finished: receiver,
+                        },
+                    );
+                    job.source = JobSource::Download(Some(finished));
+                }
+            }
+        }
+
+        duplicates
+    }
+
+    /// Downloads the posts of every collection received, until the grabber closes the channel.
+    ///
+    /// Each post is downloaded in its own task, with the `maxConcurrentDownloads` option in the config limiting how many
+    /// run at the same time. A post that fails to download is logged and skipped, so the rest of the posts are still
+    /// downloaded.
+    ///
+    /// A post that appears in more than one collection is only downloaded once, and only counted once in the progress
+    /// bar. Its other locations wait for the download to finish and are then linked to it, without taking a permit.
+    ///
+    /// # Arguments
+    ///
+    /// * `collections`: The collections sent by the grabber.
+    ///
+    /// returns: Result<Vec<`DownloadFailure`, Global>, Error>, with every post that failed to download.
+    async fn download_collections(
+        self,
+        mut collections: UnboundedReceiver<PostCollection>,
+    ) -> Result<Vec<DownloadFailure>, Error> {
+        let max_concurrent_downloads = Config::get().max_concurrent_downloads();
+        trace!("Downloading posts with up to {max_concurrent_downloads} concurrent downloads...");
+
+        let permits = Arc::new(Semaphore::new(max_concurrent_downloads));
+        let mut tasks = JoinSet::new();
+        let mut failures = Vec::new();
+        let mut downloads = HashMap::new();
+        let mut duplicates = 0;
+        while let Some(collection) = collections.recv().await {
+            let mut jobs = self.collect_download_jobs(&collection)?;
+            duplicates += Self::deduplicate_jobs(&mut jobs, &mut downloads);
+            self.progress_bar.inc_length(
+                jobs.iter()
+                    .filter(|e| matches!(e.source, JobSource::Download(_)))
+                    .filter_map(|e| e.post.file_size())
+                    .map(i64::cast_unsigned)
+                    .sum(),
+            );
+
+            for job in jobs {
+                let permit = match job.source {
+                    JobSource::Download(_) => Some(
+                        Arc::clone(&permits)
+                            .acquire_owned()
+                            .await
+                            .expect("Download permits are never closed!"),
+                    ),
+                    JobSource::Duplicate(_) => None,
+                };
+                let downloader = self.clone();
+                let permits = Arc::clone(&permits);
+                tasks.spawn(async move {
+                    let _permit = permit;
+                    let result = downloader.run_job(&job, &permits).await;
+                    downloader.finish_sync(job.collection_sync.as_deref(), result.is_ok());
+                    result.map_err(|error| {
+                        downloader.progress_bar.suspend(|| {
+                            error!(
+                                "Skipping \"{}\" as it could not be downloaded: {error}",
+                                job.file_path.to_string_lossy()
+                            );
+                        });
+                        DownloadFailure {
+                            file_path: job.file_path,
+                            error,
+                        }
+                    })
+                });
+
+                while let Some(result) = tasks.try_join_next() {
+                    failures.extend(Self::check_task(result)?);
+                }
+            }
+        }
+
+        while let Some(result) = tasks.join_next().await {
+            failures.extend(Self::check_task(result)?);
+        }
+
+        if duplicates > 0 {
+            self.progress_bar.suspend(|| {
+                info!(
+                    "Found {} posts in more than one collection, which were only downloaded once...",
+                    console::style(duplicates).cyan().italic()
+                );
+            });
+        }
+
+        Ok(failures)
+    }
+
+    /// Counts a finished download towards its collection, and marks the collection's search in the sync state once every
+    /// post of it was downloaded.
+    ///
+    /// A collection with a post that failed to download isn't marked, so the search is walked to the same point again
+    /// on the next run.
+    ///
+    /// # Arguments
+    ///
+    /// * `collection_sync`: The sync progress of the collection the post belongs to, if any.
+    /// * `downloaded`: Whether the post was downloaded.
+    fn finish_sync(&self, collection_sync: Option<&CollectionSync>, downloaded: bool) {
+        let (Some(collection_sync), Some(sync_state)) = (collection_sync, &self.sync_state) else {
+            return;
+        };
+
+        if !downloaded {
+            collection_sync.failed.store(true, Ordering::Relaxed);
+        }
+
+        if collection_sync.remaining.fetch_sub(1, Ordering::AcqRel) == 1
+            && !collection_sync.failed.load(Ordering::Relaxed)
+        {
+            sync_state
+                .lock()
+                .expect("Sync state lock was poisoned!")
+                .mark(&collection_sync.search, collection_sync.newest_id);
+        }
+    }
+
+    /// Checks the result of a finished download task.
+    ///
+    /// # Arguments
+    ///
+    /// * `result`: The result of the task.
+    ///
+    /// returns: Result<Option<`DownloadFailure`>, Error>, with the failure if the download failed.
+    fn check_task(
+        result: Result<Result<(), DownloadFailure>, JoinError>,
+    ) -> Result<Option<DownloadFailure>, Error> {
+        match result {
+            Ok(result) => Ok(result.err()),
+            Err(_) => Err(anyhow!("A download task panicked!")),
+        }
+    }
+}
+
 /// A web connector that manages how the API is called (through the [`RequestSender`]), how posts are grabbed
 /// (through [Grabber]), and how the posts are downloaded.
 pub(crate) struct E621WebConnector {
@@ -50,7 +564,7 @@ pub(crate) struct E621WebConnector {
     /// Grabber which is responsible for grabbing posts.
     grabber: Grabber,
     /// The user's blacklist.
-    blacklist: Rc<RefCell<Blacklist>>,
+    blacklist: Arc<RwLock<Blacklist>>,
 }
 
 impl E621WebConnector {
@@ -60,241 +574,220 @@ impl E621WebConnector {
             request_sender: request_sender.clone(),
             download_directory: Config::get().download_directory().to_string(),
             progress_bar: ProgressBar::hidden(),
-            grabber: Grabber::new(request_sender.clone(), false),
-            blacklist: Rc::new(RefCell::new(Blacklist::new(request_sender.clone()))),
+            grabber: Grabber::new(request_sender.clone()),
+            blacklist: Arc::new(RwLock::new(Blacklist::new(request_sender.clone()))),
         }
     }
 
-    /// Gets input and enters safe depending on user choice.
-    pub(crate) fn should_enter_safe_mode(&mut self) -> Result<(), Error> {
-        trace!("Prompt for safe mode...");
-        let confirm_prompt = Confirm::new()
-            .with_prompt("Should enter safe mode?")
-            .show_default(true)
-            .default(false)
-            .interact()
-            .with_context(|| {
-                error!("Failed to setup confirmation prompt!");
-                "Terminal unable to set up confirmation prompt..."
-            })?;
-
-        trace!("Safe mode decision: {confirm_prompt}");
-        if confirm_prompt {
-            self.request_sender.update_to_safe();
-            self.grabber.set_safe_mode(true);
-        }
-
-        Ok(())
-    }
-
     /// Processes the blacklist and tokenizes for use when grabbing posts.
-    pub(crate) fn process_blacklist(&mut self) {
+    pub(crate) fn process_blacklist(&mut self) -> E621Result<()> {
         let username = Login::get().username();
-        let user: UserEntry = self
-            .request_sender
-            .get_entry_from_appended_id(username, "user");
+        let user: UserEntry = block_on(
+            self.request_sender
+                .get_entry_from_appended_id(username, "user"),
+        )?;
         if let Some(blacklist_tags) = user.blacklisted_tags
             && !blacklist_tags.is_empty()
         {
             let blacklist = self.blacklist.clone();
             blacklist
-                .borrow_mut()
-                .parse_blacklist(blacklist_tags)
+                .write()
+                .expect("Blacklist lock was poisoned!")
+                .parse_blacklist(blacklist_tags)?
                 .cache_users();
             self.grabber.set_blacklist(blacklist);
         }
+
+        Ok(())
+    }
+
+    /// Initializes the progress bar for downloading process.
+    ///
+    /// The length starts at `0`, and grows as every collection is grabbed.
+    fn initialize_progress_bar(&mut self) {
+        self.progress_bar = ProgressBarBuilder::new(0)
+            .style(
+                ProgressStyleBuilder::default()
+                    .template("{prefix}{msg} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} {binary_bytes_per_sec} {eta}")
+                    .progress_chars("=>-")
+                    .build())
+            .draw_target(ProgressDrawTarget::stderr())
+            .reset()
+            .steady_tick(Duration::from_secs(1))
+            .build();
     }
 
-    /// Creates `Grabber` and grabs all posts before returning a tuple containing all general posts and single posts
-    /// (posts grabbed by its ID).
+    /// Grabs every post from the groups and downloads them.
+    ///
+    /// Grabbing and downloading overlap, every collection starts downloading as soon as it is grabbed, while the next
+    /// collections are still being grabbed.
     ///
     /// # Arguments
     ///
     /// * `groups`: The groups to grab from.
-    pub(crate) fn grab_all(&mut self, groups: &[Group]) {
-        trace!("Grabbing posts...");
-        self.grabber.grab_favorites();
-        self.grabber.grab_posts_by_tags(groups);
+    pub(crate) fn grab_and_download(&mut self, groups: &[Group]) -> Result<(), Error> {
+        self.initialize_progress_bar();
+        let result = block_on(self.grab_and_download_async(groups));
+        self.progress_bar.finish_and_clear();
+        result
     }
 
-    /// Saves image to download directory.
-    fn save_image(&self, file_path: &str, bytes: &[u8]) -> Result<(), Error> {
-        write(file_path, bytes).with_context(|| {
-            error!("Failed to save image!");
-            "A downloaded image was unable to be saved..."
-        })?;
-        trace!("Saved {file_path}...");
+    /// Grabs every post from the groups on the current task, while a separate task downloads what was grabbed.
+    ///
+    /// # Arguments
+    ///
+    /// * `groups`: The groups to grab from.
+    async fn grab_and_download_async(&mut self, groups: &[Group]) -> Result<(), Error> {
+        let sync_state = Config::get().incremental_sync().then(|| {
+            Arc::new(Mutex::new(SyncState::load(
+                self.request_sender.post_source(),
+                Arguments::get().full_sync(),
+            )))
+        });
+        if let Some(sync_state) = &sync_state {
+            self.grabber.set_sync_state(Arc::clone(sync_state));
+        }
+
+        let downloader = Downloader {
+            request_sender: self.request_sender.clone(),
+            download_directory: self.download_directory.clone(),
+            progress_bar: self.progress_bar.clone(),
+            sync_state: sync_state.clone(),
+        };
+        let (collections, received) = unbounded_channel();
+        let downloads = runtime().spawn(downloader.download_collections(received));
+
+        self.grabber.grab_all(groups, collections).await;
+
+        let failures = downloads
+            .await
+            .map_err(|_| anyhow!("The download task panicked!"))??;
+        self.report_failures(&failures);
+        if let Some(sync_state) = sync_state {
+            sync_state
+                .lock()
+                .expect("Sync state lock was poisoned!")
+                .save();
+        }
+
         Ok(())
     }
 
-    /// Removes invalid characters from directory path.
+    /// Reports every post that failed to download at the end of the run, with the posts that repeatedly failed md5
+    /// verification listed separately.
     ///
     /// # Arguments
     ///
-    /// * `dir_name`: Directory name to remove invalid chars from.
-    ///
-    /// returns: String
-    fn remove_invalid_chars(&self, dir_name: &str) -> String {
-        dir_name
-            .chars()
-            .map(|e| match e {
-                '?' | ':' | '*' | '<' | '>' | '\"' | '|' | '/' | '\\' => '_',
-                _ => e,
-            })
-            .collect()
-    }
-
-    /// Processes `PostSet` and downloads all posts from it.
-    fn download_collection(&mut self) -> Result<(), Error> {
-        for collection in self.grabber.posts() {
-            let collection_name = collection.name();
-            let collection_category = collection.category();
-            let collection_posts = collection.posts();
-            let collection_count = collection_posts.len();
-            let short_collection_name = collection.shorten("...");
-
-            #[cfg(unix)]
-            let static_path: PathBuf = [
-                &self.download_directory,
-                collection.category(),
-                &self.remove_invalid_chars(collection_name),
-            ]
-            .iter()
-            .collect();
-
-            #[cfg(windows)]
-            let mut static_path: PathBuf = [
-                &self.download_directory,
-                collection.category(),
-                &self.remove_invalid_chars(collection_name),
-            ]
-            .iter()
-            .collect();
+    /// * `failures`: The posts that failed to download.
+    fn report_failures(&self, failures: &[DownloadFailure]) {
+        if failures.is_empty() {
+            return;
+        }
 
-            // This is put here to attempt to shorten the length of the path if it passes window's
-            // max path length.
-            #[cfg(windows)]
-            const MAX_PATH: usize = 260; // Defined in Windows documentation.
-
-            #[cfg(windows)]
-            let start_path_len = static_path.as_os_str().len();
-
-            #[cfg(windows)]
-            if start_path_len >= MAX_PATH {
-                static_path = [
-                    &self.download_directory,
-                    collection_category,
-                    &self.remove_invalid_chars(&collection.shorten('_')),
-                ]
+        self.progress_bar.suspend(|| {
+            warn!(
+                "{} posts failed to download:",
+                console::style(failures.len()).cyan().italic()
+            );
+            let (mismatched, failed): (Vec<_>, Vec<_>) = failures
                 .iter()
-                .collect();
+                .partition(|e| matches!(e.error, E621Error::ChecksumMismatch { .. }));
+            for failure in failed {
+                warn!(
+                    "  {}: {}",
+                    failure.file_path.to_string_lossy(),
+                    failure.error
+                );
+            }
 
-                let new_len = static_path.as_os_str().len();
-                if new_len >= MAX_PATH {
-                    error!(
-                        "Path is too long and crosses the {MAX_PATH} char limit.\
-                       Please relocate the program to a directory closer to the root drive directory."
-                    );
-                    trace!("Path length: {new_len}");
+            if !mismatched.is_empty() {
+                warn!(
+                    "{} posts failed md5 verification on every attempt:",
+                    console::style(mismatched.len()).cyan().italic()
+                );
+                for failure in mismatched {
+                    warn!("  {}", failure.file_path.to_string_lossy());
                 }
             }
+        });
+    }
+}
 
-            trace!("Printing Collection Info:");
-            trace!("Collection Name:            \"{collection_name}\"");
-            trace!("Collection Category:        \"{collection_category}\"");
-            trace!("Collection Post Length:     \"{collection_count}\"");
-            trace!(
-                "Static file path for this collection: \"{}\"",
-                static_path.to_string_lossy()
-            );
-
-            for post in collection_posts {
-                let static_path_str = static_path
-                    .to_str()
-                    .context("Path contains invalid UTF-8")?;
-                let file_path: PathBuf = [static_path_str, &self.remove_invalid_chars(post.name())]
-                    .iter()
-                    .collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::e621::sender::entries::PostEntry;
 
-                if file_path.exists() {
-                    self.progress_bar
-                        .set_message("Duplicate found: skipping... ");
-                    self.progress_bar.inc(post.file_size().cast_unsigned());
-                    continue;
-                }
+    /// Creates a job that downloads the given variant of a post with the given md5.
+    fn job(id: i64, md5: &str, variant: FileVariant) -> DownloadJob {
+        let mut post = PostEntry {
+            id,
+            ..PostEntry::default()
+        };
+        post.file.md5 = md5.to_string();
+        post.file.ext = String::from("png");
+        post.file.url = Some(format!("https://static1.e621.net/data/{md5}.png"));
+        post.sample.has = Some(true);
+        post.sample.url = Some(format!("https://static1.e621.net/data/sample/{md5}.jpg"));
 
-                self.progress_bar
-                    .set_message(format!("Downloading: {short_collection_name} "));
-
-                let parent_path = file_path
-                    .parent()
-                    .context("Failed to get parent directory")?;
-                create_dir_all(parent_path).with_context(|| {
-                    error!("Could not create directories for images!");
-                    format!(
-                        "Directory path unable to be created...\nPath: \"{}\"",
-                        parent_path.to_string_lossy()
-                    )
-                })?;
-
-                let bytes = self
-                    .request_sender
-                    .download_image(post.url(), post.file_size());
-                self.save_image(
-                    file_path
-                        .to_str()
-                        .context("File path contains invalid UTF-8")?,
-                    &bytes,
-                )?;
-                self.progress_bar.inc(post.file_size().cast_unsigned());
-            }
-
-            trace!("Collection {collection_name} is finished downloading...");
+        let post = GrabbedPost::from((post, "id", variant));
+        DownloadJob {
+            file_path: PathBuf::from(post.name()),
+            post,
+            collection_name: String::from("Test"),
+            collection_sync: None,
+            source: JobSource::Download(None),
         }
+    }
 
-        Ok(())
+    /// Gets the path of the job a duplicate is linked to, or `None` if the job is downloaded.
+    fn duplicate_of(job: &DownloadJob) -> Option<&Path> {
+        match &job.source {
+            JobSource::Download(_) => None,
+            JobSource::Duplicate(original) => Some(&original.file_path),
+        }
     }
 
-    /// Initializes the progress bar for downloading process.
-    ///
-    /// # Arguments
-    ///
-    /// * `len`: The total bytes to download.
-    fn initialize_progress_bar(&mut self, len: u64) {
-        self.progress_bar = ProgressBarBuilder::new(len)
-            .style(
-                ProgressStyleBuilder::default()
-                    .template("{msg} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} {binary_bytes_per_sec} {eta}")
-                    .progress_chars("=>-")
-                    .build())
-            .draw_target(ProgressDrawTarget::stderr())
-            .reset()
-            .steady_tick(Duration::from_secs(1))
-            .build();
+    #[test]
+    fn deduplicate_jobs_links_posts_queued_earlier() {
+        let mut downloads = HashMap::new();
+        let mut first = [job(1, "aa", FileVariant::Original)];
+        assert_eq!(Downloader::deduplicate_jobs(&mut first, &mut downloads), 0);
+        assert!(matches!(first[0].source, JobSource::Download(Some(_))));
+
+        let mut second = [
+            job(1, "aa", FileVariant::Original),
+            job(2, "bb", FileVariant::Original),
+            job(2, "bb", FileVariant::Original),
+        ];
+        assert_eq!(Downloader::deduplicate_jobs(&mut second, &mut downloads), 2);
+        assert_eq!(duplicate_of(&second[0]), Some(Path::new("1.png")));
+        assert_eq!(duplicate_of(&second[1]), None);
+        assert_eq!(duplicate_of(&second[2]), Some(Path::new("2.png")));
     }
 
-    /// Downloads tuple of general posts and single posts.
-    pub(crate) fn download_posts(&mut self) -> Result<(), Error> {
-        // Initializes the progress bar for downloading.
-        let length = self.get_total_file_size();
-        trace!("Total file size for all images grabbed is {length}KB");
-        self.initialize_progress_bar(length);
-        let result = self.download_collection();
-        self.progress_bar.finish_and_clear();
-        result
+    #[test]
+    fn deduplicate_jobs_keeps_variants_of_a_post_apart() {
+        let mut downloads = HashMap::new();
+        let mut jobs = [
+            job(1, "aa", FileVariant::Original),
+            job(1, "aa", FileVariant::Sample),
+            job(1, "aa", FileVariant::Sample),
+        ];
+        assert_eq!(Downloader::deduplicate_jobs(&mut jobs, &mut downloads), 1);
+        assert_eq!(duplicate_of(&jobs[0]), None);
+        assert_eq!(duplicate_of(&jobs[1]), None);
+        assert_eq!(duplicate_of(&jobs[2]), Some(Path::new("1_sample.jpg")));
     }
 
-    /// Gets the total size (in KB) of every post image to be downloaded.
-    fn get_total_file_size(&self) -> u64 {
-        self.grabber
-            .posts()
-            .iter()
-            .map(|e| {
-                e.posts()
-                    .iter()
-                    .map(|f| f.file_size().cast_unsigned())
-                    .sum::<u64>()
-            })
-            .sum()
+    #[test]
+    fn deduplicate_jobs_always_downloads_posts_without_an_md5() {
+        let mut downloads = HashMap::new();
+        let mut jobs = [
+            job(1, "", FileVariant::Original),
+            job(1, "", FileVariant::Original),
+        ];
+        assert_eq!(Downloader::deduplicate_jobs(&mut jobs, &mut downloads), 0);
+        assert!(downloads.is_empty());
     }
 }
@@ -0,0 +1,161 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use indicatif::{BinaryBytes, ProgressBar};
+use time::OffsetDateTime;
+use tokio::time::sleep;
+
+use crate::e621::io::BandwidthConfig;
+
+/// How long a paused download sleeps before checking if its window has ended.
+const PAUSE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The bandwidth cap that applies at a point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Cap {
+    /// Downloads run at full speed.
+    Unlimited,
+    /// Downloads are capped to this many bytes per second.
+    BytesPerSecond(u64),
+    /// Downloads are paused until the window ends at the given time of day.
+    Paused(String),
+}
+
+impl Cap {
+    /// The prefix shown on the progress bar while the cap applies.
+    fn prefix(&self) -> String {
+        match self {
+            Cap::Unlimited => String::new(),
+            Cap::BytesPerSecond(rate) => format!("[Throttled to {}/s] ", BinaryBytes(*rate)),
+            Cap::Paused(end) => format!("[Paused until {end}] "),
+        }
+    }
+}
+
+/// The current state of the byte bucket.
+struct Bucket {
+    /// The bytes currently available. This goes negative when bytes are reserved ahead of time.
+    bytes: f64,
+    /// The last time the bucket was refilled.
+    last_refill: Instant,
+    /// The cap that applied the last time bytes were taken, used to only update the progress bar when it changes.
+    cap: Cap,
+}
+
+/// A token bucket that caps how many bytes per second are downloaded across every download worker.
+///
+/// This works the same as the [`RateLimiter`](super::limiter::RateLimiter), except that a token is a byte and the rate
+/// changes with the time of day depending on the windows in the config.
+pub(crate) struct BandwidthLimiter {
+    /// The caps and windows from the config.
+    config: BandwidthConfig,
+    /// The bucket holding the bytes.
+    bucket: Mutex<Bucket>,
+}
+
+impl BandwidthLimiter {
+    /// Creates a new bandwidth limiter with a full bucket.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: The bandwidth section of the config.
+    ///
+    /// returns: `BandwidthLimiter`
+    pub(crate) fn new(config: &BandwidthConfig) -> Self {
+        BandwidthLimiter {
+            config: config.clone(),
+            bucket: Mutex::new(Bucket {
+                bytes: config.bytes_per_second().unwrap_or_default() as f64,
+                last_refill: Instant::now(),
+                cap: Cap::Unlimited,
+            }),
+        }
+    }
+
+    /// Gets the cap that applies right now, based on the local time of day.
+    fn current_cap(&self) -> Cap {
+        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        let minute_of_day = u16::from(now.hour()) * 60 + u16::from(now.minute());
+        match self
+            .config
+            .windows()
+            .iter()
+            .find(|window| window.contains(minute_of_day))
+        {
+            Some(window) if window.is_paused() => Cap::Paused(window.end().to_string()),
+            Some(window) => window
+                .bytes_per_second()
+                .map_or(Cap::Unlimited, Cap::BytesPerSecond),
+            None => self
+                .config
+                .bytes_per_second()
+                .map_or(Cap::Unlimited, Cap::BytesPerSecond),
+        }
+    }
+
+    /// Takes bytes from the bucket, blocking until they are available or until a pause ends.
+    ///
+    /// Passing `0` bytes only waits out a pause, which is used before a download is started.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes`: The amount of bytes that were downloaded.
+    /// * `progress_bar`: The progress bar to show the cap on.
+    pub(crate) async fn consume(&self, bytes: usize, progress_bar: &ProgressBar) {
+        loop {
+            let cap = self.current_cap();
+            let wait = {
+                let mut bucket = self.bucket.lock().expect("Bandwidth lock was poisoned!");
+                if bucket.cap != cap {
+                    trace!("Bandwidth cap changed to {cap:?}...");
+                    progress_bar.set_prefix(cap.prefix());
+                    bucket.cap = cap.clone();
+                }
+
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.last_refill = now;
+                match cap {
+                    Cap::Unlimited => return,
+                    Cap::Paused(_) => None,
+                    Cap::BytesPerSecond(rate) => {
+                        let rate = rate as f64;
+                        bucket.bytes = (bucket.bytes + elapsed * rate).min(rate) - bytes as f64;
+                        Some(
+                            (bucket.bytes < 0.0)
+                                .then(|| Duration::from_secs_f64(-bucket.bytes / rate)),
+                        )
+                    }
+                }
+            };
+
+            match wait {
+                // Paused, so check again once the interval has passed.
+                None => sleep(PAUSE_CHECK_INTERVAL).await,
+                Some(wait) => {
+                    if let Some(wait) = wait {
+                        sleep(wait).await;
+                    }
+
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,294 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use reqwest::header::{AUTHORIZATION, RANGE};
+use reqwest::{Body, Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string_pretty};
+use tokio::fs::{File, create_dir_all, read_to_string, remove_file, try_exists, write};
+use tokio::io::AsyncWriteExt;
+
+use crate::e621::error::{E621Error, Result};
+use crate::e621::io::state_file::stable_hash;
+use crate::e621::io::{CassetteConfig, CassetteMode};
+
+/// The request a recording was made for.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct RecordedRequest {
+    /// The method of the request (e.g "GET").
+    method: String,
+    /// The full url of the request, including the query.
+    url: String,
+    /// The range of the file that was requested, if the request resumed a download.
+    range: Option<String>,
+    /// Whether the request was sent with the user's credentials.
+    #[serde(default)]
+    authenticated: bool,
+}
+
+impl RecordedRequest {
+    /// Gets the parts of a request that identify it in the cassette.
+    ///
+    /// Only whether the request was authenticated is kept, so credentials never end up in a cassette while the same url
+    /// requested with and without them is still recorded twice.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: The request to identify.
+    ///
+    /// returns: `RecordedRequest`
+    fn new(request: &Request) -> Self {
+        RecordedRequest {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            range: request
+                .headers()
+                .get(RANGE)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from),
+            authenticated: request.headers().contains_key(AUTHORIZATION),
+        }
+    }
+
+    /// The name the recording is saved under, which is a FNV-1a hash of the request.
+    fn key(&self) -> String {
+        let identity = format!(
+            "{} {} {} {}",
+            self.method,
+            self.url,
+            self.range.as_deref().unwrap_or_default(),
+            self.authenticated
+        );
+        stable_hash(&identity)
+    }
+}
+
+/// A response saved to the cassette. The body is saved next to it in its own file.
+#[derive(Serialize, Deserialize, Debug)]
+struct Recording {
+    /// The request the response was for.
+    request: RecordedRequest,
+    /// The status code of the response.
+    status: u16,
+    /// The headers of the response.
+    headers: Vec<(String, String)>,
+}
+
+/// A directory of recorded responses, which requests are either recorded to or replayed from.
+///
+/// Every response is saved as a `<key>.json` file describing the request and response, along with a `<key>.body` file
+/// holding the raw body.
+pub(crate) struct Cassette {
+    /// Whether requests are recorded or replayed.
+    mode: CassetteMode,
+    /// The directory the cassette is saved in.
+    directory: PathBuf,
+}
+
+impl Cassette {
+    /// Creates the cassette described by the config, if one is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: The cassette section of the config.
+    ///
+    /// returns: Option<Cassette>
+    pub(crate) fn new(config: &CassetteConfig) -> Option<Self> {
+        match config.mode() {
+            CassetteMode::Off => None,
+            mode => {
+                info!(
+                    "Cassette is in {mode:?} mode using directory \"{}\"...",
+                    config.directory()
+                );
+                Some(Cassette {
+                    mode,
+                    directory: PathBuf::from(config.directory()),
+                })
+            }
+        }
+    }
+
+    /// Whether responses are served from the cassette instead of the network.
+    pub(crate) fn is_replaying(&self) -> bool {
+        self.mode == CassetteMode::Replay
+    }
+
+    /// Gets the paths of the metadata and body files of a recording.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: The request the recording is for.
+    ///
+    /// returns: (PathBuf, PathBuf)
+    fn paths(&self, request: &RecordedRequest) -> (PathBuf, PathBuf) {
+        let key = request.key();
+        (
+            self.directory.join(format!("{key}.json")),
+            self.directory.join(format!("{key}.body")),
+        )
+    }
+
+    /// Saves a response to the cassette, then hands back an identical response for the caller to read.
+    ///
+    /// The body is streamed to the cassette as it arrives, and the returned response reads it back from there, so a
+    /// large download is never held in memory. The outer result holds errors from writing the cassette, while the
+    /// inner result holds the error that interrupted reading the body, if there was one.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: The request that was sent.
+    /// * `response`: The response to the request.
+    ///
+    /// returns: Result<Result<Response, reqwest::Error>, E621Error>
+    pub(crate) async fn record(
+        &self,
+        request: &Request,
+        mut response: Response,
+    ) -> Result<reqwest::Result<Response>> {
+        let request = RecordedRequest::new(request);
+        let status = response.status();
+        let headers: Vec<(String, String)> = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+
+        let (recording_path, body_path) = self.paths(&request);
+        trace!(
+            "Recording {} {} to the cassette...",
+            request.method, request.url
+        );
+        create_dir_all(&self.directory)
+            .await
+            .map_err(|e| E621Error::filesystem(&self.directory, e))?;
+        let mut body = File::create(&body_path)
+            .await
+            .map_err(|e| E621Error::filesystem(&body_path, e))?;
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => body
+                    .write_all(&chunk)
+                    .await
+                    .map_err(|e| E621Error::filesystem(&body_path, e))?,
+                Ok(None) => break,
+                Err(error) => {
+                    // A partial body is never replayed, since the recording it belongs to is never written.
+                    drop(body);
+                    let _ = remove_file(&body_path).await;
+                    return Ok(Err(error));
+                }
+            }
+        }
+        body.flush()
+            .await
+            .map_err(|e| E621Error::filesystem(&body_path, e))?;
+        drop(body);
+
+        let recording = Recording {
+            request,
+            status: status.as_u16(),
+            headers,
+        };
+        let json = to_string_pretty(&recording)
+            .map_err(|e| E621Error::deserialize("cassette recording", e))?;
+        write(&recording_path, json)
+            .await
+            .map_err(|e| E621Error::filesystem(&recording_path, e))?;
+
+        Ok(Ok(Self::build_response(&recording, &body_path).await?))
+    }
+
+    /// Loads the recorded response to a request from the cassette.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: The request to replay.
+    ///
+    /// returns: Result<Response, E621Error>
+    pub(crate) async fn replay(&self, request: &Request) -> Result<Response> {
+        let request = RecordedRequest::new(request);
+        let (recording_path, body_path) = self.paths(&request);
+        if !try_exists(&recording_path).await.unwrap_or(false) {
+            error!(
+                "No recording of {} {} was found!",
+                request.method, request.url
+            );
+            return Err(E621Error::Network {
+                url: Some(request.url),
+                source: io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "request is missing from the cassette",
+                )
+                .into(),
+            });
+        }
+
+        trace!(
+            "Replaying {} {} from the cassette...",
+            request.method, request.url
+        );
+        let recording: Recording =
+            from_str(&Self::read_string(&recording_path).await?).map_err(|e| {
+                E621Error::deserialize(format!("cassette recording for {}", request.url), e)
+            })?;
+        Self::build_response(&recording, &body_path).await
+    }
+
+    /// Reads a file from the cassette to a string.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the file.
+    ///
+    /// returns: Result<String, E621Error>
+    async fn read_string(path: &Path) -> Result<String> {
+        read_to_string(path)
+            .await
+            .map_err(|e| E621Error::filesystem(path, e))
+    }
+
+    /// Builds a response out of a recording, with a body that is streamed from the recorded body file.
+    ///
+    /// # Arguments
+    ///
+    /// * `recording`: The recorded status and headers.
+    /// * `body_path`: The path of the recorded body.
+    ///
+    /// returns: Result<Response, E621Error>
+    async fn build_response(recording: &Recording, body_path: &Path) -> Result<Response> {
+        let body = File::open(body_path)
+            .await
+            .map_err(|e| E621Error::filesystem(body_path, e))?;
+        let mut builder = http::Response::builder().status(recording.status);
+        for (name, value) in &recording.headers {
+            builder = builder.header(name, value);
+        }
+
+        builder
+            .body(Body::from(body))
+            .map(Response::from)
+            .map_err(|e| {
+                E621Error::deserialize(
+                    format!("cassette recording for {}", recording.request.url),
+                    e,
+                )
+            })
+    }
+}
@@ -0,0 +1,88 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::time::sleep;
+
+use crate::e621::io::RateLimit;
+
+/// The current state of the token bucket.
+struct Bucket {
+    /// The tokens currently available. This goes negative when tokens are reserved ahead of time.
+    tokens: f64,
+    /// The last time the bucket was refilled.
+    last_refill: Instant,
+}
+
+/// A token bucket rate limiter that is shared between every thread sending requests.
+///
+/// Each request takes a token from the bucket, which refills at a steady rate up to its burst size. When the bucket is
+/// empty, the token is reserved ahead of time and the caller sleeps until it would have been refilled, so waiting
+/// callers are let through in the order they arrived. Sleeping only suspends the caller's task, not the thread.
+pub(crate) struct RateLimiter {
+    /// How many tokens are refilled per second.
+    rate: f64,
+    /// The maximum amount of tokens the bucket can hold.
+    burst: f64,
+    /// The bucket holding the tokens.
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    /// Creates a new rate limiter with a full bucket.
+    ///
+    /// # Arguments
+    ///
+    /// * `rate_limit`: The rate and burst of the limiter.
+    ///
+    /// returns: `RateLimiter`
+    pub(crate) fn new(rate_limit: &RateLimit) -> Self {
+        let burst = f64::from(rate_limit.burst());
+        RateLimiter {
+            rate: rate_limit.requests_per_second(),
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token from the bucket, blocking until one is available.
+    pub(crate) async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.bucket.lock().expect("Rate limiter lock was poisoned!");
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+            bucket.last_refill = now;
+            bucket.tokens -= 1.0;
+
+            if bucket.tokens < 0.0 {
+                Duration::from_secs_f64(-bucket.tokens / self.rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+
+        if !wait.is_zero() {
+            trace!("Rate limit reached, waiting {}ms...", wait.as_millis());
+            sleep(wait).await;
+        }
+    }
+}
@@ -15,21 +15,37 @@
  */
 
 use std::any::type_name;
-use std::cell::RefCell;
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use anyhow::{Context, Result};
-use reqwest::blocking::{Client, RequestBuilder, Response};
-use reqwest::header::{AUTHORIZATION, USER_AGENT};
+use indicatif::ProgressBar;
+use reqwest::header::{AUTHORIZATION, CONTENT_RANGE, RANGE, USER_AGENT};
+use reqwest::{Client, NoProxy, Proxy, Request, RequestBuilder, Response, StatusCode, Version};
 use serde::de::DeserializeOwned;
-use serde_json::{Value, from_value};
+use serde_json::{Value, from_slice, from_value};
+use tokio::fs::{File, OpenOptions, metadata, remove_file, rename};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::{Instant, sleep, timeout_at};
 
-use crate::e621::io::{Login, emergency_exit};
-use crate::e621::sender::entries::{AliasEntry, BulkPostEntry, PostEntry, TagEntry};
+use crate::e621::error::{E621Error, Result};
+use crate::e621::io::{Config, Login, ProtocolMode, ProxyConfig};
+use crate::e621::sender::bandwidth::BandwidthLimiter;
+use crate::e621::sender::cassette::Cassette;
+use crate::e621::sender::entries::{AliasEntry, BulkPostEntry, TagEntry, UserEntry};
+use crate::e621::sender::limiter::RateLimiter;
+use crate::e621::sender::stats::{PendingRequest, RequestStats};
 
+pub(crate) mod bandwidth;
+pub(crate) mod cassette;
 pub(crate) mod entries;
+pub(crate) mod limiter;
+pub(crate) mod retry;
+pub(crate) mod runtime;
+pub(crate) mod stats;
 
 /// Creates a hashmap through similar syntax of the `vec` macro.
 ///
@@ -70,38 +86,196 @@ const USER_AGENT_VALUE: &str = concat!(
     " on e621)"
 );
 
+/// A page of a bulk search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SearchPage {
+    /// A page by its number, starting at `1`. The API doesn't allow going past page `750`.
+    Numbered(u16),
+    /// The page of posts with an ID lower than the given one, which can walk through every post of a search.
+    Before(i64),
+}
+
+impl Display for SearchPage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchPage::Numbered(page) => write!(f, "{page}"),
+            SearchPage::Before(id) => write!(f, "b{id}"),
+        }
+    }
+}
+
+/// The result of checking the login against the server.
+pub(crate) enum LoginStatus {
+    /// No username or API key was given, so requests are sent anonymously.
+    Anonymous,
+    /// The login is valid, with the name of the user it belongs to.
+    Valid(String),
+    /// The username was given, but the API key is empty.
+    MissingApiKey,
+    /// The API key was given, but the username is empty.
+    MissingUsername,
+    /// No user exists with the username.
+    UnknownUsername,
+    /// The user exists, but the API key doesn't belong to them.
+    InvalidApiKey,
+    /// The server refused the login, which happens when the account is banned or the API key lacks access.
+    Forbidden,
+}
+
 /// A reference counted client used for all searches by the [Grabber], [Blacklist], [`E621WebConnector`], etc.
+///
+/// The client is atomically reference counted, so it can be shared between the download tasks. Every request is sent
+/// asynchronously on the [runtime](runtime::runtime), with [`block_on`](runtime::block_on) as the way in for
+/// synchronous code.
 struct SenderClient {
-    /// [Client] wrapped in a [Rc] so only one instance of the client exists. This will prevent an overabundance of
+    /// [Client] wrapped in an [Arc] so only one instance of the client exists. This will prevent an overabundance of
     /// clients in the code.
-    client: Rc<Client>,
+    client: Arc<Client>,
     /// The base64 encrypted username and password of the user. This is passed only through the [AUTHORIZATION] header
     /// of the request and is a highly secured method of login through client.
-    auth: Rc<String>,
+    auth: Arc<String>,
+    /// The cassette requests are recorded to or replayed from, if one is enabled in the config.
+    cassette: Option<Arc<Cassette>>,
+    /// The HTTP/1.1 client requests fall back to if HTTP/2 fails, unless the client only uses HTTP/1.1.
+    fallback: Option<Arc<Http1Fallback>>,
+}
+
+/// A client that only uses HTTP/1.1, which every request to a host is sent through once an HTTP/2 handshake with it
+/// fails.
+struct Http1Fallback {
+    /// The HTTP/1.1 client.
+    client: Client,
+    /// The protocol found to work with every host a request was sent to (e.g the API and the file server).
+    hosts: Mutex<HashMap<String, HostProtocol>>,
+}
+
+/// The protocol found to work with a host.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HostProtocol {
+    /// A response was received over HTTP/2, which means the host supports it and later errors aren't caused by the
+    /// handshake.
+    Http2,
+    /// The HTTP/2 handshake failed, so requests to the host are sent through the fallback.
+    Http1,
+}
+
+impl Http1Fallback {
+    /// Gets the protocol found to work with a host, if any request was sent to it yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `host`: The host to check.
+    ///
+    /// returns: Option<`HostProtocol`>
+    fn protocol(&self, host: &str) -> Option<HostProtocol> {
+        self.hosts
+            .lock()
+            .expect("The host protocols were poisoned!")
+            .get(host)
+            .copied()
+    }
+
+    /// Sets the protocol found to work with a host.
+    ///
+    /// # Arguments
+    ///
+    /// * `host`: The host the protocol was found for.
+    /// * `protocol`: The protocol that works with it.
+    ///
+    /// returns: Option<`HostProtocol`>, which is the protocol the host had before.
+    fn set_protocol(&self, host: &str, protocol: HostProtocol) -> Option<HostProtocol> {
+        self.hosts
+            .lock()
+            .expect("The host protocols were poisoned!")
+            .insert(host.to_string(), protocol)
+    }
 }
 
 impl SenderClient {
     /// Creates root client.
-    fn new(auth: String) -> Self {
+    fn new(auth: String) -> Result<Self> {
         trace!("SenderClient initializing with USER_AGENT_VALUE \"{USER_AGENT_VALUE}\"");
 
-        SenderClient {
-            client: Rc::new(SenderClient::build_client()),
-            auth: Rc::new(auth),
+        let protocol = Config::get().protocol();
+        trace!("SenderClient sending requests with protocol mode {protocol:?}");
+        if let Some(proxy) = Config::get().proxy() {
+            info!(
+                "Sending all requests through proxy {}...",
+                proxy.redacted_url()
+            );
         }
+
+        let fallback = match protocol {
+            ProtocolMode::Http1 => None,
+            ProtocolMode::Auto | ProtocolMode::Http2 => Some(Arc::new(Http1Fallback {
+                client: SenderClient::build_client(ProtocolMode::Http1)?,
+                hosts: Mutex::new(HashMap::new()),
+            })),
+        };
+
+        Ok(SenderClient {
+            client: Arc::new(SenderClient::build_client(protocol)?),
+            fallback,
+            auth: Arc::new(auth),
+            cassette: Cassette::new(Config::get().cassette()).map(Arc::new),
+        })
     }
 
     /// Runs client through a builder to give it required settings.
-    /// Cookies aren't stored in the client, `TCP_NODELAY` is on, and timeout is changed from 30 seconds to 60.
-    fn build_client() -> Client {
-        Client::builder()
+    /// Cookies aren't stored in the client, `TCP_NODELAY` is on, and the connect and read-idle timeouts come from the
+    /// config. There is no overall timeout on the client, since downloads can take much longer than API calls.
+    /// If a proxy is set in the config, every request is sent through it.
+    ///
+    /// # Arguments
+    ///
+    /// * `protocol`: Which HTTP version the client sends requests with.
+    ///
+    /// returns: Result<Client, E621Error>
+    fn build_client(protocol: ProtocolMode) -> Result<Client> {
+        let config = Config::get();
+        let mut builder = Client::builder()
             .use_rustls_tls()
-            .http2_prior_knowledge()
             .tcp_keepalive(Duration::from_secs(30))
             .tcp_nodelay(true)
-            .timeout(Duration::from_secs(60))
-            .build()
-            .unwrap_or_else(|_| Client::new())
+            .connect_timeout(config.timeouts().connect())
+            .read_timeout(config.timeouts().read_idle());
+        builder = match protocol {
+            ProtocolMode::Auto => builder,
+            ProtocolMode::Http1 => builder.http1_only(),
+            ProtocolMode::Http2 => builder.http2_prior_knowledge(),
+        };
+
+        if let Some(proxy) = config.proxy() {
+            builder = builder.proxy(SenderClient::build_proxy(proxy)?);
+        }
+
+        builder.build().map_err(|e| {
+            error!("Unable to build the client!");
+            e.into()
+        })
+    }
+
+    /// Builds the proxy requests are sent through from the config.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: The proxy section of the config.
+    ///
+    /// returns: Result<Proxy, E621Error>
+    fn build_proxy(config: &ProxyConfig) -> Result<Proxy> {
+        let mut proxy = Proxy::all(config.url()).map_err(|e| {
+            error!("Unable to use proxy {}!", config.redacted_url());
+            E621Error::from(e)
+        })?;
+        if let Some(username) = config.username() {
+            proxy = proxy.basic_auth(username, config.password().unwrap_or_default());
+        }
+
+        if !config.no_proxy().is_empty() {
+            proxy = proxy.no_proxy(NoProxy::from_string(&config.no_proxy().join(",")));
+        }
+
+        Ok(proxy)
     }
 
     /// A wrapping function that acts the exact same as `self.client.get` but will instead attach the user agent header
@@ -130,15 +304,113 @@ impl SenderClient {
             self.get(url).header(AUTHORIZATION, self.auth.as_str())
         }
     }
+
+    /// Whether responses are replayed from a cassette instead of being sent over the network.
+    fn is_replaying(&self) -> bool {
+        self.cassette.as_ref().is_some_and(|e| e.is_replaying())
+    }
+
+    /// Sends a request, recording its response to the cassette or replaying it from the cassette if one is enabled.
+    ///
+    /// The outer result holds errors from the cassette, while the inner result holds the error from sending the
+    /// request.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: The request to send.
+    ///
+    /// returns: Result<Result<Response, reqwest::Error>, E621Error>
+    async fn execute(&self, request: Request) -> Result<reqwest::Result<Response>> {
+        match &self.cassette {
+            Some(cassette) if cassette.is_replaying() => Ok(Ok(cassette.replay(&request).await?)),
+            Some(cassette) => {
+                let recorded = request
+                    .try_clone()
+                    .expect("Requests without a body can always be cloned!");
+                match self.send(request).await {
+                    Ok(response) => cassette.record(&recorded, response).await,
+                    Err(error) => Ok(Err(error)),
+                }
+            }
+            None => Ok(self.send(request).await),
+        }
+    }
+
+    /// Sends a request over the network, falling back to HTTP/1.1 for its host for the rest of the run if the HTTP/2
+    /// handshake with it fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: The request to send.
+    ///
+    /// returns: Result<Response, reqwest::Error>
+    async fn send(&self, request: Request) -> reqwest::Result<Response> {
+        let Some(fallback) = &self.fallback else {
+            return self.client.execute(request).await;
+        };
+
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        let protocol = fallback.protocol(&host);
+        if protocol == Some(HostProtocol::Http1) {
+            return fallback.client.execute(request).await;
+        }
+
+        let retry = request.try_clone();
+        match self.client.execute(request).await {
+            Ok(response) => {
+                if protocol.is_none() && response.version() == Version::HTTP_2 {
+                    fallback.set_protocol(&host, HostProtocol::Http2);
+                }
+
+                Ok(response)
+            }
+            Err(error) if protocol != Some(HostProtocol::Http2) && Self::is_http2_error(&error) => {
+                if fallback.set_protocol(&host, HostProtocol::Http1) != Some(HostProtocol::Http1) {
+                    warn!(
+                        "HTTP/2 failed with {host} ({error}), falling back to HTTP/1.1 for it for the rest of the \
+                         run..."
+                    );
+                }
+
+                match retry {
+                    Some(request) => fallback.client.execute(request).await,
+                    None => Err(error),
+                }
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Checks if an error was caused by HTTP/2, by looking for an HTTP/2 error in its sources.
+    ///
+    /// # Arguments
+    ///
+    /// * `error`: The error to check.
+    ///
+    /// returns: bool
+    fn is_http2_error(error: &reqwest::Error) -> bool {
+        let mut source = error.source();
+        while let Some(error) = source {
+            if error.downcast_ref::<h2::Error>().is_some() {
+                return true;
+            }
+
+            source = error.source();
+        }
+
+        false
+    }
 }
 
 impl Clone for SenderClient {
-    /// Creates a new instance of `SenderClient`, but clones the [Rc] of the root client, ensuring that all requests are
+    /// Creates a new instance of `SenderClient`, but clones the [Arc] of the root client, ensuring that all requests are
     /// going to the same client.
     fn clone(&self) -> Self {
         SenderClient {
-            client: Rc::clone(&self.client),
-            auth: Rc::clone(&self.auth),
+            client: Arc::clone(&self.client),
+            auth: Arc::clone(&self.auth),
+            cassette: self.cassette.clone(),
+            fallback: self.fallback.clone(),
         }
     }
 }
@@ -149,15 +421,23 @@ impl Clone for SenderClient {
 pub(crate) struct RequestSender {
     /// The client that will be used to send all requests.
     ///
-    /// Even though the [`SenderClient`] isn't wrapped in a [Rc], the main client inside of it is, this will ensure that
+    /// Even though the [`SenderClient`] isn't wrapped in an [Arc], the main client inside of it is, this will ensure that
     /// all request are only sent through one client.
     client: SenderClient,
     /// All available urls to use with the sender.
-    urls: Rc<RefCell<HashMap<String, String>>>,
+    urls: Arc<HashMap<String, String>>,
+    /// The rate limiter shared by all API calls.
+    api_limiter: Arc<RateLimiter>,
+    /// The rate limiter shared by all file downloads.
+    download_limiter: Arc<RateLimiter>,
+    /// The bandwidth cap shared by all file downloads.
+    bandwidth_limiter: Arc<BandwidthLimiter>,
+    /// Every request sent, shared by all clones of the sender.
+    stats: Arc<RequestStats>,
 }
 
 impl RequestSender {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new() -> Result<Self> {
         let login = Login::get();
         let auth = if login.is_empty() {
             String::new()
@@ -165,25 +445,71 @@ impl RequestSender {
             base64_url::encode(format!("{}:{}", login.username(), login.api_key()).as_str())
         };
 
-        RequestSender {
-            client: SenderClient::new(auth),
-            urls: Rc::new(RefCell::new(RequestSender::initialize_url_map())),
-        }
+        Ok(RequestSender {
+            client: SenderClient::new(auth)?,
+            urls: Arc::new(RequestSender::initialize_url_map(
+                Config::get().api_base_url(),
+            )),
+            api_limiter: Arc::new(RateLimiter::new(Config::get().api_rate_limit())),
+            download_limiter: Arc::new(RateLimiter::new(Config::get().download_rate_limit())),
+            bandwidth_limiter: Arc::new(BandwidthLimiter::new(Config::get().bandwidth())),
+            stats: Arc::new(RequestStats::default()),
+        })
     }
 
     /// Initializes all the urls that will be used by the sender.
-    fn initialize_url_map() -> HashMap<String, String> {
-        hashmap![
-            ("posts", "https://e621.net/posts.json"),
-            ("pool", "https://e621.net/pools/"),
-            ("set", "https://e621.net/post_sets/"),
-            ("single", "https://e621.net/posts/"),
-            ("blacklist", "https://e621.net/users/"),
-            ("tag", "https://e621.net/tags/"),
-            ("tag_bulk", "https://e621.net/tags.json"),
-            ("alias", "https://e621.net/tag_aliases.json"),
-            ("user", "https://e621.net/users/")
-        ]
+    ///
+    /// Every endpoint path is joined to the base url, with the paths in the config's `endpoints` replacing the defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url`: The host all API calls are sent to.
+    ///
+    /// returns: HashMap<String, String>
+    fn initialize_url_map(base_url: &str) -> HashMap<String, String> {
+        let mut urls = hashmap![
+            ("posts", "/posts.json"),
+            ("pool", "/pools/"),
+            ("set", "/post_sets/"),
+            ("single", "/posts/"),
+            ("blacklist", "/users/"),
+            ("tag", "/tags/"),
+            ("tag_bulk", "/tags.json"),
+            ("alias", "/tag_aliases.json"),
+            ("user", "/users/")
+        ];
+
+        for (endpoint, path) in Config::get().endpoints() {
+            match urls.get_mut(endpoint) {
+                Some(url) => url.clone_from(path),
+                None => warn!("Unknown endpoint \"{endpoint}\" in config, ignoring it..."),
+            }
+        }
+
+        urls.values_mut()
+            .for_each(|path| *path = format!("{base_url}{path}"));
+        urls
+    }
+
+    /// Gets the url tied to the given key.
+    ///
+    /// # Arguments
+    ///
+    /// * `url_type_key`: The type of url to get.
+    ///
+    /// returns: String
+    fn url(&self, url_type_key: &str) -> String {
+        self.urls[url_type_key].clone()
+    }
+
+    /// The url tags are looked up from, which identifies the host the lookups are made against.
+    pub(crate) fn tag_source(&self) -> String {
+        self.url("tag_bulk")
+    }
+
+    /// The url posts are searched from, which identifies the host the searches are made against.
+    pub(crate) fn post_source(&self) -> String {
+        self.url("posts")
     }
 
     /// If the client authenticated or not.
@@ -191,31 +517,55 @@ impl RequestSender {
         !self.client.auth.is_empty()
     }
 
-    /// Updates all the urls from e621 to e926.
-    pub(crate) fn update_to_safe(&mut self) {
-        self.urls
-            .borrow_mut()
-            .iter_mut()
-            .for_each(|(_, value)| *value = value.replace("e621", "e926"));
+    /// Stops sending the login with requests, so every request after is anonymous.
+    ///
+    /// This only affects this sender and the clones made from it afterward.
+    pub(crate) fn set_anonymous(&mut self) {
+        self.client.auth = Arc::new(String::new());
     }
 
-    /// If a request failed, this will output what type of error it is before exiting.
+    /// Logs a summary of every request sent so far, and saves them to the HAR file if one is set in the config.
+    pub(crate) fn report_requests(&self) {
+        self.stats.log_summary();
+        if let Some(har_file) = Config::get().har_file()
+            && let Err(error) = self.stats.save_har(Path::new(har_file))
+        {
+            error!("The request log could not be saved: {error}");
+        }
+    }
+
+    /// If a request failed, this will output what type of error it is before returning it.
     ///
     /// # Arguments
     ///
     /// * `error`: The type of error thrown.
-    fn output_error(&self, error: &reqwest::Error) {
+    ///
+    /// returns: `E621Error`
+    fn output_error(&self, error: E621Error) -> E621Error {
         error!(
             "Error occurred from sent request. \
              Error: {error}",
         );
-        trace!("Url where error occurred: {:#?}", error.url());
 
-        if let Some(status) = error.status() {
+        if let (E621Error::Network { source, .. }, Some(proxy)) = (&error, Config::get().proxy())
+            && source
+                .downcast_ref::<reqwest::Error>()
+                .is_some_and(reqwest::Error::is_connect)
+        {
+            error!(
+                "Unable to connect through the proxy {}, check that it is reachable and that the proxy \
+                 settings in the config are correct.",
+                proxy.redacted_url()
+            );
+        }
+
+        if let E621Error::HttpStatus { status, .. } = &error {
             const SERVER_INTERNAL: u16 = 500;
             const SERVER_RATE_LIMIT: u16 = 503;
             const CLIENT_FORBIDDEN: u16 = 403;
+            const CLIENT_NOT_FOUND: u16 = 404;
             const CLIENT_THROTTLED: u16 = 421;
+            const PROXY_AUTHENTICATION: u16 = 407;
 
             let code = status.as_u16();
             trace!("The response code from the server was: {code}");
@@ -234,12 +584,21 @@ impl RequestSender {
                          issue."
                     );
                 }
+                CLIENT_NOT_FOUND => {
+                    error!("The requested page or file could not be found on the server.");
+                }
                 CLIENT_FORBIDDEN => {
                     error!(
                         "The client was forbidden from accessing the api, contact the \
                          developer immediately if this error occurs."
                     );
                 }
+                PROXY_AUTHENTICATION => {
+                    error!(
+                        "The proxy refused the request as it requires authentication, check the proxy \
+                         username and password in the config."
+                    );
+                }
                 CLIENT_THROTTLED => {
                     error!(
                         "The user is throttled, thus the request is unsuccessful. \
@@ -252,44 +611,584 @@ impl RequestSender {
             }
         }
 
-        emergency_exit("To prevent the program from crashing, it will do an emergency exit.");
+        error
     }
 
-    /// Gets the response from a sent request and checks to ensure it was successful.
+    /// Sends a request once the rate limit allows it, retrying it with exponential backoff if it fails for a
+    /// temporary reason.
+    ///
+    /// Only permanent failures, or temporary failures that are still happening after the last attempt, are returned as
+    /// errors. Responses with a status in `passthrough` are returned as is, for the caller to handle (e.g a `416` when
+    /// resuming a file).
+    ///
+    /// Every response is handed to `read`, and if reading it fails for a temporary reason (e.g the connection dropping
+    /// halfway through the body) the whole request is sent again with the same backoff.
+    ///
+    /// Every response and retry is kept in `pending`, which the caller records in the request stats once the body is
+    /// read.
     ///
     /// # Arguments
     ///
-    /// * `result`: The result to check.
+    /// * `request`: The request to send.
+    /// * `limiter`: The rate limiter the request has to go through.
+    /// * `passthrough`: The statuses that are handled by the caller instead of being returned as errors.
+    /// * `pending`: The request as it is recorded in the request stats.
+    /// * `read`: Reads what the caller needs from a response that wasn't an error.
     ///
-    /// returns: Response
-    fn check_response(&self, result: Result<Response, reqwest::Error>) -> Response {
-        match result {
-            Ok(response) => response,
-            Err(ref error) => {
-                self.output_error(error);
-                unreachable!()
+    /// returns: Result<T, E621Error>
+    async fn send_request<T>(
+        &self,
+        request: &Request,
+        limiter: &RateLimiter,
+        passthrough: &[StatusCode],
+        pending: &mut PendingRequest,
+        mut read: impl AsyncFnMut(Response) -> reqwest::Result<T>,
+    ) -> Result<T> {
+        let url = request.url().to_string();
+        let policy = Config::get().retry_policy();
+        let mut attempt = 1;
+        loop {
+            if !self.client.is_replaying() {
+                limiter.acquire().await;
+            }
+
+            let result = self
+                .client
+                .execute(
+                    request
+                        .try_clone()
+                        .expect("Requests without a body can always be cloned!"),
+                )
+                .await?;
+            if let Ok(response) = &result {
+                pending.responded(response);
+            }
+
+            let delay = match result {
+                Ok(response)
+                    if retry::is_transient_status(response.status())
+                        && attempt < policy.max_attempts() =>
+                {
+                    warn!(
+                        "Request to {url} failed with status {}...",
+                        response.status()
+                    );
+                    retry::retry_after(policy, &response)
+                        .unwrap_or_else(|| retry::backoff_delay(policy, attempt))
+                }
+                Ok(response)
+                    if response.status().is_success()
+                        || passthrough.contains(&response.status()) =>
+                {
+                    match read(response).await {
+                        Ok(value) => return Ok(value),
+                        Err(error)
+                            if retry::is_transient_error(&error)
+                                && attempt < policy.max_attempts() =>
+                        {
+                            warn!("Reading the response from {url} failed: {error}...");
+                            retry::backoff_delay(policy, attempt)
+                        }
+                        Err(error) => {
+                            return Err(self.output_error(E621Error::Network {
+                                url: Some(url),
+                                source: error.into(),
+                            }));
+                        }
+                    }
+                }
+                Ok(response) => {
+                    return Err(self.output_error(E621Error::HttpStatus {
+                        url,
+                        status: response.status(),
+                    }));
+                }
+                Err(error)
+                    if retry::is_transient_error(&error) && attempt < policy.max_attempts() =>
+                {
+                    warn!("Request failed: {error}...");
+                    retry::backoff_delay(policy, attempt)
+                }
+                Err(error) => return Err(self.output_error(error.into())),
+            };
+
+            info!(
+                "Retrying in {:.1}s (attempt {} of {})...",
+                delay.as_secs_f64(),
+                attempt + 1,
+                policy.max_attempts()
+            );
+            sleep(delay).await;
+            attempt += 1;
+            pending.retried();
+        }
+    }
+
+    /// Sends an API call through the API rate limit and reads its body, aborting it if it takes longer than the request
+    /// timeout.
+    ///
+    /// The call is recorded in the request stats once its body is read, or once it fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint`: The endpoint the call is sent to.
+    /// * `request`: The request to send.
+    /// * `passthrough`: The statuses that are handled by the caller instead of being returned as errors.
+    ///
+    /// returns: Result<(StatusCode, Vec<u8>), E621Error>
+    async fn send_api_request(
+        &self,
+        endpoint: &str,
+        request: RequestBuilder,
+        passthrough: &[StatusCode],
+    ) -> Result<(StatusCode, Vec<u8>)> {
+        let request = request
+            .timeout(Config::get().timeouts().request())
+            .build()?;
+        let mut pending = PendingRequest::new(endpoint, &request);
+        let result = self
+            .read_api_response(&request, passthrough, &mut pending)
+            .await;
+        self.stats.record(pending, result.as_ref().err());
+        result
+    }
+
+    /// Sends an API call and reads the whole body of its response, sending it again if the body fails to download.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: The request to send.
+    /// * `passthrough`: The statuses that are handled by the caller instead of being returned as errors.
+    /// * `pending`: The call as it is recorded in the request stats.
+    ///
+    /// returns: Result<(StatusCode, Vec<u8>), E621Error>
+    async fn read_api_response(
+        &self,
+        request: &Request,
+        passthrough: &[StatusCode],
+        pending: &mut PendingRequest,
+    ) -> Result<(StatusCode, Vec<u8>)> {
+        let (status, body) = self
+            .send_request(
+                request,
+                &self.api_limiter,
+                passthrough,
+                pending,
+                async |response| {
+                    let status = response.status();
+                    Ok((status, response.bytes().await?))
+                },
+            )
+            .await?;
+        pending.received(body.len() as u64);
+        Ok((status, Vec::from(body)))
+    }
+
+    /// Sends a file download request through the download rate limit.
+    ///
+    /// A `416` is returned as is, since it is handled by the downloader when resuming a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: The request to send.
+    /// * `pending`: The download as it is recorded in the request stats.
+    ///
+    /// returns: Result<Response, E621Error>
+    async fn send_download_request(
+        &self,
+        request: RequestBuilder,
+        pending: &mut PendingRequest,
+    ) -> Result<Response> {
+        self.send_request(
+            &request.build()?,
+            &self.download_limiter,
+            &[StatusCode::RANGE_NOT_SATISFIABLE],
+            pending,
+            async |response| Ok(response),
+        )
+        .await
+    }
+
+    /// Sends an API call and deserializes the json it responds with.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint`: The endpoint the call is sent to.
+    /// * `request`: The request to send.
+    ///
+    /// returns: Result<T, E621Error>
+    async fn get_json<T>(&self, endpoint: &str, request: RequestBuilder) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let url = request
+            .try_clone()
+            .and_then(|e| e.build().ok())
+            .map_or_else(String::new, |e| e.url().to_string());
+        let (_, body) = self.send_api_request(endpoint, request, &[]).await?;
+        from_slice(&body).map_err(|e| {
+            error!("Unable to deserialize json to \"{}\"!", type_name::<T>());
+            E621Error::deserialize(format!("response from {url}"), e)
+        })
+    }
+
+    /// Gets the path of the partial file a download is streamed into before it is complete.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path`: The path the finished file will be saved to.
+    ///
+    /// returns: PathBuf
+    pub(crate) fn part_path(file_path: &Path) -> PathBuf {
+        let mut part_path = file_path.as_os_str().to_owned();
+        part_path.push(".part");
+        PathBuf::from(part_path)
+    }
+
+    /// Checks whether the server answered a range request with the remainder of the file, starting at `resume_from`.
+    ///
+    /// # Arguments
+    ///
+    /// * `response`: The response to the range request.
+    /// * `resume_from`: The byte the download was requested to resume from.
+    ///
+    /// returns: bool
+    fn is_resumed_response(response: &Response, resume_from: u64) -> bool {
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            return false;
+        }
+
+        response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("bytes "))
+            .and_then(|value| value.split('-').next())
+            .and_then(|start| start.parse::<u64>().ok())
+            .is_some_and(|start| start == resume_from)
+    }
+
+    /// Sends request to download image and streams it to disk.
+    ///
+    /// The file is written in chunks to a `.part` file next to `file_path`, synced, and then renamed into place, so a
+    /// download that is cut short never leaves a truncated file behind. If a `.part` file is already there, the
+    /// download resumes from its length with a range request, and starts over if the server ignores the range or
+    /// answers with a different one. When the connection drops in the middle of the transfer, or the transfer stalls
+    /// or passes its overall timeout, the download is retried and resumed by the retry policy.
+    ///
+    /// The file is hashed as it streams, and when `expected_md5` is given, a file that doesn't match it is deleted and
+    /// downloaded again, up to the max attempts of the retry policy.
+    ///
+    /// The download is recorded in the request stats once it finishes, with every resume and new attempt counted as a
+    /// retry of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url to the file to download.
+    /// * `expected_md5`: The md5 the downloaded file should have.
+    /// * `expected_size`: The size of the file, if it was already added to the length of the progress bar. Otherwise,
+    ///   the length is added once the server responds with it.
+    /// * `file_path`: The path to save the file to.
+    /// * `progress_bar`: The progress bar to update after every chunk.
+    ///
+    /// returns: Result<(), E621Error>
+    pub(crate) async fn download_image(
+        &self,
+        url: &str,
+        expected_md5: Option<&str>,
+        expected_size: Option<u64>,
+        file_path: &Path,
+        progress_bar: &ProgressBar,
+    ) -> Result<()> {
+        let mut pending = PendingRequest::new("download", &self.client.get(url).build()?);
+        let result = self
+            .download_to_part_file(
+                url,
+                expected_md5,
+                expected_size,
+                file_path,
+                progress_bar,
+                &mut pending,
+            )
+            .await;
+        self.stats.record(pending, result.as_ref().err());
+        result?;
+
+        rename(Self::part_path(file_path), file_path)
+            .await
+            .map_err(|e| {
+                error!("Failed to save image!");
+                E621Error::filesystem(file_path, e)
+            })?;
+        trace!("Saved {}...", file_path.to_string_lossy());
+
+        Ok(())
+    }
+
+    /// Downloads a file into its `.part` file, resuming it when the transfer is interrupted and downloading it again
+    /// when it doesn't match `expected_md5`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url to the file to download.
+    /// * `expected_md5`: The md5 the downloaded file should have.
+    /// * `expected_size`: The size of the file, if it was already added to the length of the progress bar.
+    /// * `file_path`: The path the file will be saved to.
+    /// * `progress_bar`: The progress bar to update after every chunk.
+    /// * `pending`: The download as it is recorded in the request stats.
+    ///
+    /// returns: Result<(), E621Error>
+    async fn download_to_part_file(
+        &self,
+        url: &str,
+        expected_md5: Option<&str>,
+        expected_size: Option<u64>,
+        file_path: &Path,
+        progress_bar: &ProgressBar,
+        pending: &mut PendingRequest,
+    ) -> Result<()> {
+        let part_path = Self::part_path(file_path);
+        let policy = Config::get().retry_policy();
+        let mut progress = DownloadProgress::new(expected_size.is_some());
+        let mut verify_attempt = 1;
+        loop {
+            let mut context = md5::Context::new();
+            let mut attempt = 1;
+            while let Err(error) = self
+                .stream_to_part_file(
+                    url,
+                    &part_path,
+                    progress_bar,
+                    &mut progress,
+                    &mut context,
+                    pending,
+                )
+                .await?
+            {
+                if attempt >= policy.max_attempts() {
+                    error!("Download was interrupted {attempt} times, giving up on {url}...");
+                    return Err(error);
+                }
+
+                let delay = retry::backoff_delay(policy, attempt);
+                warn!("Download of {url} was interrupted: {error}...");
+                info!(
+                    "Resuming in {:.1}s (attempt {} of {})...",
+                    delay.as_secs_f64(),
+                    attempt + 1,
+                    policy.max_attempts()
+                );
+                sleep(delay).await;
+                attempt += 1;
+                pending.retried();
+            }
+
+            let actual = format!("{:x}", context.finalize());
+            let Some(expected) = expected_md5.filter(|e| !e.eq_ignore_ascii_case(&actual)) else {
+                break;
+            };
+
+            remove_file(&part_path)
+                .await
+                .map_err(|e| E621Error::filesystem(&part_path, e))?;
+            if verify_attempt >= policy.max_attempts() {
+                error!("Download of {url} failed md5 verification {verify_attempt} times...");
+                return Err(E621Error::ChecksumMismatch {
+                    path: file_path.to_path_buf(),
+                    expected: expected.to_string(),
+                    actual,
+                });
+            }
+
+            warn!("Download of {url} has md5 {actual}, but {expected} was expected...");
+            info!(
+                "Downloading again (attempt {} of {})...",
+                verify_attempt + 1,
+                policy.max_attempts()
+            );
+            verify_attempt += 1;
+            pending.retried();
+        }
+
+        Ok(())
+    }
+
+    /// Hashes the contents of a `.part` file that is about to be resumed, so the md5 of the finished file covers the
+    /// bytes downloaded by an earlier attempt.
+    ///
+    /// # Arguments
+    ///
+    /// * `part_path`: The path of the `.part` file.
+    ///
+    /// returns: Result<Context, E621Error>
+    async fn hash_part_file(part_path: &Path) -> Result<md5::Context> {
+        let mut part_file = File::open(part_path)
+            .await
+            .map_err(|e| E621Error::filesystem(part_path, e))?;
+        let mut context = md5::Context::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = part_file
+                .read(&mut buffer)
+                .await
+                .map_err(|e| E621Error::filesystem(part_path, e))?;
+            if read == 0 {
+                return Ok(context);
             }
+
+            context.consume(&buffer[..read]);
         }
     }
 
-    /// Sends request to download image.
+    /// Streams a file into its `.part` file, resuming from the end of the file if it already exists.
+    ///
+    /// The outer result holds errors that can't be fixed by trying again (e.g the disk), while the inner result holds
+    /// the error that interrupted the transfer, if there was one.
     ///
     /// # Arguments
     ///
     /// * `url`: The url to the file to download.
-    /// * `file_size`: The file size of the file.
+    /// * `part_path`: The path of the `.part` file to stream into.
+    /// * `progress_bar`: The progress bar to update after every chunk.
+    /// * `progress`: How much of the file was already reported to the progress bar.
+    /// * `context`: The md5 of the `.part` file, which is updated after every chunk.
+    /// * `pending`: The download as it is recorded in the request stats.
     ///
-    /// returns: Vec<u8, Global>
-    pub(crate) fn download_image(&self, url: &str, file_size: i64) -> Vec<u8> {
-        let mut image_response = self.check_response(self.client.get(url).send());
-        let mut image_bytes: Vec<u8> =
-            Vec::with_capacity(usize::try_from(file_size).expect("Something went wrong."));
-        image_response
-            .copy_to(&mut image_bytes)
-            .with_context(|| "Failed to download image!".to_string())
-            .unwrap();
+    /// returns: Result<Result<(), E621Error>, E621Error>
+    async fn stream_to_part_file(
+        &self,
+        url: &str,
+        part_path: &Path,
+        progress_bar: &ProgressBar,
+        progress: &mut DownloadProgress,
+        context: &mut md5::Context,
+        pending: &mut PendingRequest,
+    ) -> Result<Result<()>> {
+        let (mut image_response, resume_from) = loop {
+            let resume_from = metadata(part_path).await.map_or(0, |e| e.len());
+
+            let mut request = self.client.get(url);
+            if resume_from > 0 {
+                request = request.header(RANGE, format!("bytes={resume_from}-"));
+            }
 
-        image_bytes
+            self.bandwidth_limiter.consume(0, progress_bar).await;
+            let image_response = self.send_download_request(request, pending).await?;
+            // A range that can't be satisfied, or a partial response that doesn't start where the file ends, can't be
+            // appended to the partial file, so the file is downloaded again without a range.
+            let status = image_response.status();
+            if resume_from > 0
+                && (status == StatusCode::RANGE_NOT_SATISFIABLE
+                    || (status == StatusCode::PARTIAL_CONTENT
+                        && !Self::is_resumed_response(&image_response, resume_from)))
+            {
+                trace!(
+                    "Partial file \"{}\" could not be resumed, starting over...",
+                    part_path.to_string_lossy()
+                );
+                remove_file(part_path)
+                    .await
+                    .map_err(|e| E621Error::filesystem(part_path, e))?;
+                pending.retried();
+                continue;
+            }
+
+            break (image_response, resume_from);
+        };
+
+        let (part_file, mut offset) =
+            if resume_from > 0 && Self::is_resumed_response(&image_response, resume_from) {
+                trace!(
+                    "Resuming \"{}\" from byte {resume_from}...",
+                    part_path.to_string_lossy()
+                );
+                progress.report(progress_bar, resume_from);
+                *context = Self::hash_part_file(part_path).await?;
+                (
+                    OpenOptions::new().append(true).open(part_path).await,
+                    resume_from,
+                )
+            } else {
+                if resume_from > 0 {
+                    trace!("Server ignored the range request, starting download over...");
+                }
+
+                *context = md5::Context::new();
+                (File::create(part_path).await, 0)
+            };
+        let mut part_file = part_file.map_err(|e| {
+            error!("Failed to open partial file!");
+            E621Error::filesystem(part_path, e)
+        })?;
+        let timeouts = Config::get().timeouts();
+        let overall_timeout = image_response
+            .content_length()
+            .and_then(|length| timeouts.download(length));
+        let mut deadline = overall_timeout.map(|e| Instant::now() + e);
+        if let Some(length) = image_response.content_length() {
+            progress.add_length(progress_bar, offset + length);
+        }
+
+        loop {
+            let idle_deadline = Instant::now() + timeouts.read_idle();
+            let chunk = match timeout_at(
+                deadline.map_or(idle_deadline, |e| e.min(idle_deadline)),
+                image_response.chunk(),
+            )
+            .await
+            {
+                Ok(Ok(Some(chunk))) => chunk,
+                Ok(Ok(None)) => break,
+                interrupted => {
+                    let error = match interrupted {
+                        Ok(Err(error)) => E621Error::Network {
+                            url: Some(url.to_string()),
+                            source: error.into(),
+                        },
+                        _ if deadline.is_some_and(|e| Instant::now() >= e) => E621Error::TimedOut {
+                            url: url.to_string(),
+                            after: overall_timeout.unwrap_or_default(),
+                        },
+                        _ => E621Error::Stalled {
+                            url: url.to_string(),
+                            idle: timeouts.read_idle(),
+                        },
+                    };
+
+                    // The chunks written so far have to reach the file before the next attempt reads its length.
+                    part_file
+                        .flush()
+                        .await
+                        .map_err(|e| E621Error::filesystem(part_path, e))?;
+                    return Ok(Err(error));
+                }
+            };
+
+            part_file.write_all(&chunk).await.map_err(|e| {
+                error!("A downloaded chunk was unable to be saved...");
+                E621Error::filesystem(part_path, e)
+            })?;
+            context.consume(&chunk);
+            offset += chunk.len() as u64;
+            pending.received(chunk.len() as u64);
+            progress.report(progress_bar, offset);
+
+            // Time spent throttled by the bandwidth cap doesn't count towards the overall timeout.
+            let throttled_at = Instant::now();
+            self.bandwidth_limiter
+                .consume(chunk.len(), progress_bar)
+                .await;
+            if let Some(deadline) = &mut deadline {
+                *deadline += throttled_at.elapsed();
+            }
+        }
+
+        part_file.sync_all().await.map_err(|e| {
+            error!("A downloaded image was unable to be synced to disk...");
+            E621Error::filesystem(part_path, e)
+        })?;
+
+        Ok(Ok(()))
     }
 
     /// Appends base url with id/name before ending with `.json`.
@@ -304,6 +1203,53 @@ impl RequestSender {
         format!("{url}{append}.json")
     }
 
+    /// Checks the login against the server with an authenticated request for the user.
+    ///
+    /// If the server refuses the login, the user is requested again anonymously to tell whether the username or the
+    /// API key is wrong.
+    ///
+    /// returns: Result<`LoginStatus`, E621Error>
+    pub(crate) async fn verify_login(&self) -> Result<LoginStatus> {
+        const LOGIN_STATUSES: &[StatusCode] = &[
+            StatusCode::UNAUTHORIZED,
+            StatusCode::FORBIDDEN,
+            StatusCode::NOT_FOUND,
+        ];
+
+        let login = Login::get();
+        match (login.username().is_empty(), login.api_key().is_empty()) {
+            (true, true) => return Ok(LoginStatus::Anonymous),
+            (false, true) => return Ok(LoginStatus::MissingApiKey),
+            (true, false) => return Ok(LoginStatus::MissingUsername),
+            (false, false) => {}
+        }
+
+        let url = self.append_url(&self.url("user"), login.username());
+        let (status, body) = self
+            .send_api_request("user", self.client.get_with_auth(&url), LOGIN_STATUSES)
+            .await?;
+        trace!("Login check responded with {status}");
+        match status {
+            StatusCode::UNAUTHORIZED => {
+                let (status, _) = self
+                    .send_api_request("user", self.client.get(&url), LOGIN_STATUSES)
+                    .await?;
+                if status == StatusCode::NOT_FOUND {
+                    Ok(LoginStatus::UnknownUsername)
+                } else {
+                    Ok(LoginStatus::InvalidApiKey)
+                }
+            }
+            StatusCode::FORBIDDEN => Ok(LoginStatus::Forbidden),
+            StatusCode::NOT_FOUND => Ok(LoginStatus::UnknownUsername),
+            _ => {
+                let user: UserEntry = from_slice(&body)
+                    .map_err(|e| E621Error::deserialize(format!("response from {url}"), e))?;
+                Ok(LoginStatus::Valid(user.name))
+            }
+        }
+    }
+
     /// Gets entry by type `T`, this is used for every request where the url needs to be appended to.
     ///
     /// # Arguments
@@ -311,51 +1257,35 @@ impl RequestSender {
     /// * `id`: The id to search for.
     /// * `url_type_key`: The type of url to use.
     ///
-    /// returns: T
-    pub(crate) fn get_entry_from_appended_id<T>(&self, id: &str, url_type_key: &str) -> T
+    /// returns: Result<T, E621Error>
+    pub(crate) async fn get_entry_from_appended_id<T>(
+        &self,
+        id: &str,
+        url_type_key: &str,
+    ) -> Result<T>
     where
         T: DeserializeOwned,
     {
         let value: Value = self
-            .check_response(
+            .get_json(
+                url_type_key,
                 self.client
-                    .get_with_auth(&self.append_url(&self.urls.borrow()[url_type_key], id))
-                    .send(),
+                    .get_with_auth(&self.append_url(&self.url(url_type_key), id)),
             )
-            .json()
-            .with_context(|| {
-                format!(
-                    "Json was unable to deserialize to \"{}\"!\n\
-                     url_type_key: {}\n\
-                     id: {}",
-                    type_name::<Value>(),
-                    url_type_key,
-                    id
-                )
-            })
-            .unwrap();
+            .await?;
 
         let value = match url_type_key {
-            "single" => value
-                .get("post")
-                .unwrap_or_else(|| {
-                    emergency_exit(&format!(
-                        "Post was not found! Post ID ({id}) is invalid or post was deleted."
-                    ));
-                    unreachable!()
-                })
-                .to_owned(),
+            "single" => value.get("post").cloned().ok_or_else(|| {
+                error!("Post was not found! Post ID ({id}) is invalid or post was deleted.");
+                E621Error::deserialize(format!("post {id}"), "the response did not contain a post")
+            })?,
             _ => value,
         };
 
-        from_value(value)
-            .with_context(|| {
-                error!("Could not convert entry to type \"{}\"!", type_name::<T>());
-                "Unexpected error occurred when trying to perform conversion from value to entry \
-                type above."
-                    .to_string()
-            })
-            .unwrap()
+        from_value(value).map_err(|e| {
+            error!("Could not convert entry to type \"{}\"!", type_name::<T>());
+            E621Error::deserialize(format!("{url_type_key} entry {id}"), e)
+        })
     }
 
     /// Performs a bulk search for posts using tags to filter the response.
@@ -365,29 +1295,23 @@ impl RequestSender {
     /// * `searching_tag`: The tags for filtering.
     /// * `page`: The page to search for.
     ///
-    /// returns: `BulkPostEntry`
-    pub(crate) fn bulk_search(&self, searching_tag: &str, page: u16) -> BulkPostEntry {
+    /// returns: Result<`BulkPostEntry`, E621Error>
+    pub(crate) async fn bulk_search(
+        &self,
+        searching_tag: &str,
+        page: SearchPage,
+    ) -> Result<BulkPostEntry> {
         debug!("Downloading page {page} of tag {searching_tag}");
 
-        self.check_response(
-            self.client
-                .get_with_auth(&self.urls.borrow()["posts"])
-                .query(&[
-                    ("tags", searching_tag),
-                    ("page", &format!("{page}")),
-                    ("limit", &320.to_string()),
-                ])
-                .send(),
+        self.get_json(
+            "posts",
+            self.client.get_with_auth(&self.url("posts")).query(&[
+                ("tags", searching_tag),
+                ("page", &page.to_string()),
+                ("limit", &320.to_string()),
+            ]),
         )
-        .json()
-        .with_context(|| {
-            error!(
-                "Unable to deserialize json to \"{}\"!",
-                type_name::<Vec<PostEntry>>()
-            );
-            "Failed to perform bulk search...".to_string()
-        })
-        .unwrap()
+        .await
     }
 
     /// Gets tags by their name.
@@ -396,75 +1320,120 @@ impl RequestSender {
     ///
     /// * `tag`: The name of the tag.
     ///
-    /// returns: Vec<`TagEntry`, Global>
-    pub(crate) fn get_tags_by_name(&self, tag: &str) -> Vec<TagEntry> {
+    /// returns: Result<Vec<`TagEntry`, Global>, E621Error>
+    pub(crate) async fn get_tags_by_name(&self, tag: &str) -> Result<Vec<TagEntry>> {
+        self.get_tags_by_names(&[tag]).await
+    }
+
+    /// Gets every tag matching one of the given names in a single API call.
+    ///
+    /// Names that don't exist are left out of the result, and the names of the tags returned are lowercase.
+    ///
+    /// # Arguments
+    ///
+    /// * `tags`: The names of the tags.
+    ///
+    /// returns: Result<Vec<`TagEntry`, Global>, E621Error>
+    pub(crate) async fn get_tags_by_names(&self, tags: &[&str]) -> Result<Vec<TagEntry>> {
+        let names = tags.join(",");
         let result: Value = self
-            .check_response(
-                self.client
-                    .get(&self.urls.borrow()["tag_bulk"])
-                    .query(&[("search[name]", tag)])
-                    .send(),
+            .get_json(
+                "tag_bulk",
+                self.client.get(&self.url("tag_bulk")).query(&[
+                    ("search[name]", names.as_str()),
+                    ("limit", &tags.len().to_string()),
+                ]),
             )
-            .json()
-            .with_context(|| {
-                format!(
-                    "Json was unable to deserialize to \"{}\"!\n\
-                     url_type_key: tag_bulk\n\
-                     tag: {}",
-                    type_name::<Value>(),
-                    tag
-                )
-            })
-            .unwrap();
+            .await?;
         if result.is_object() {
-            vec![]
+            Ok(vec![])
         } else {
-            from_value::<Vec<TagEntry>>(result)
-                .with_context(|| {
-                    error!(
-                        "Unable to deserialize Value to \"{}\"!",
-                        type_name::<Vec<TagEntry>>()
-                    );
-                    "Failed to perform bulk search...".to_string()
-                })
-                .unwrap()
+            from_value::<Vec<TagEntry>>(result).map_err(|e| {
+                error!(
+                    "Unable to deserialize Value to \"{}\"!",
+                    type_name::<Vec<TagEntry>>()
+                );
+                E621Error::deserialize(format!("tags named {names}"), e)
+            })
         }
     }
 
-    /// Queries aliases and returns response.
+    /// Gets every alias of one of the given names, ordered by their status.
+    ///
+    /// The aliases are requested a page at a time, until a page comes back with less aliases than the limit.
     ///
     /// # Arguments
     ///
-    /// * `tag`: The alias to search for.
+    /// * `tags`: The aliases to search for.
     ///
-    /// returns: Option<Vec<`AliasEntry`, Global>>
+    /// returns: Result<Vec<`AliasEntry`, Global>, E621Error>
+    pub(crate) async fn query_aliases_by_names(&self, tags: &[&str]) -> Result<Vec<AliasEntry>> {
+        const ALIAS_LIMIT: usize = 320;
+
+        let names = tags.join(",");
+        let limit = ALIAS_LIMIT.to_string();
+        let mut aliases = Vec::new();
+        for page in 1.. {
+            let result: Value = self
+                .get_json(
+                    "alias",
+                    self.client.get(&self.url("alias")).query(&[
+                        ("search[antecedent_name]", names.as_str()),
+                        ("search[order]", "status"),
+                        ("limit", limit.as_str()),
+                        ("page", &page.to_string()),
+                    ]),
+                )
+                .await?;
+            if result.is_object() {
+                break;
+            }
+
+            let entries = from_value::<Vec<AliasEntry>>(result).map_err(|e| {
+                error!(
+                    "Unable to deserialize Value to \"{}\"!",
+                    type_name::<Vec<AliasEntry>>()
+                );
+                E621Error::deserialize(format!("aliases of {names}"), e)
+            })?;
+            let last_page = entries.len() < ALIAS_LIMIT;
+            aliases.extend(entries);
+            if last_page {
+                break;
+            }
+        }
+
+        Ok(aliases)
+    }
+
+    /// Queries aliases and returns response.
     ///
-    /// # Examples
+    /// # Arguments
     ///
-    /// ```
+    /// * `tag`: The alias to search for.
     ///
-    /// ```
-    pub(crate) fn query_aliases(&self, tag: &str) -> Option<Vec<AliasEntry>> {
-        let result = self
-            .check_response(
-                self.client
-                    .get(&self.urls.borrow()["alias"])
-                    .query(&[
-                        ("commit", "Search"),
-                        ("search[name_matches]", tag),
-                        ("search[order]", "status"),
-                    ])
-                    .send(),
+    /// returns: Result<Option<Vec<`AliasEntry`, Global>>, E621Error>
+    pub(crate) async fn query_aliases(&self, tag: &str) -> Result<Option<Vec<AliasEntry>>> {
+        let (_, body) = self
+            .send_api_request(
+                "alias",
+                self.client.get(&self.url("alias")).query(&[
+                    ("commit", "Search"),
+                    ("search[name_matches]", tag),
+                    ("search[order]", "status"),
+                ]),
+                &[],
             )
-            .json::<Vec<AliasEntry>>();
+            .await?;
+        let result = from_slice::<Vec<AliasEntry>>(&body);
 
         match result {
-            Ok(e) => Some(e),
+            Ok(e) => Ok(Some(e)),
             Err(e) => {
                 trace!("No alias was found for {tag}...");
                 trace!("Printing trace message for why None was returned...");
                 trace!("{e}");
-                None
+                Ok(None)
             }
         }
     }
@@ -474,7 +1443,104 @@ impl Clone for RequestSender {
     fn clone(&self) -> Self {
         RequestSender {
             client: self.client.clone(),
-            urls: Rc::clone(&self.urls),
+            urls: Arc::clone(&self.urls),
+            api_limiter: Arc::clone(&self.api_limiter),
+            download_limiter: Arc::clone(&self.download_limiter),
+            bandwidth_limiter: Arc::clone(&self.bandwidth_limiter),
+            stats: Arc::clone(&self.stats),
+        }
+    }
+}
+
+/// How much of a download was reported to the progress bar, which is kept between the attempts of the download.
+struct DownloadProgress {
+    /// How many bytes of the file were reported.
+    reported: u64,
+    /// Whether the size of the file was added to the length of the progress bar.
+    length_added: bool,
+}
+
+impl DownloadProgress {
+    /// Creates the progress of a download that hasn't started yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `length_added`: Whether the size of the file was already added to the length of the progress bar.
+    fn new(length_added: bool) -> Self {
+        DownloadProgress {
+            reported: 0,
+            length_added,
+        }
+    }
+
+    /// Moves the progress bar forward to the given offset of the file being downloaded.
+    ///
+    /// Bytes that were already reported by an earlier attempt of the same download aren't counted twice.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress_bar`: The progress bar to update.
+    /// * `offset`: How many bytes of the file are now on disk.
+    fn report(&mut self, progress_bar: &ProgressBar, offset: u64) {
+        if offset > self.reported {
+            progress_bar.inc(offset - self.reported);
+            self.reported = offset;
         }
     }
+
+    /// Adds the size of the file to the length of the progress bar, if it wasn't added already.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress_bar`: The progress bar to update.
+    /// * `length`: The size of the file.
+    fn add_length(&mut self, progress_bar: &ProgressBar, length: u64) {
+        if !self.length_added {
+            progress_bar.inc_length(length);
+            self.length_added = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a response to a range request.
+    fn range_response(status: StatusCode, content_range: Option<&str>) -> Response {
+        let mut builder = http::Response::builder().status(status);
+        if let Some(content_range) = content_range {
+            builder = builder.header(CONTENT_RANGE, content_range);
+        }
+
+        Response::from(
+            builder
+                .body(String::new())
+                .expect("The response could not be built!"),
+        )
+    }
+
+    #[test]
+    fn resumed_response_starts_at_the_requested_byte() {
+        let response = range_response(StatusCode::PARTIAL_CONTENT, Some("bytes 1024-2047/2048"));
+        assert!(RequestSender::is_resumed_response(&response, 1024));
+    }
+
+    #[test]
+    fn resumed_response_rejects_a_different_range() {
+        let response = range_response(StatusCode::PARTIAL_CONTENT, Some("bytes 0-2047/2048"));
+        assert!(!RequestSender::is_resumed_response(&response, 1024));
+
+        let response = range_response(StatusCode::PARTIAL_CONTENT, Some("bytes */2048"));
+        assert!(!RequestSender::is_resumed_response(&response, 1024));
+
+        let response = range_response(StatusCode::PARTIAL_CONTENT, None);
+        assert!(!RequestSender::is_resumed_response(&response, 1024));
+    }
+
+    #[test]
+    fn resumed_response_rejects_a_whole_file() {
+        let response = range_response(StatusCode::OK, Some("bytes 1024-2047/2048"));
+        assert!(!RequestSender::is_resumed_response(&response, 1024));
+    }
 }
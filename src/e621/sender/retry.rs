@@ -0,0 +1,184 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode};
+
+use crate::e621::io::RetryPolicy;
+
+/// Checks if a response status is a temporary failure that is worth retrying.
+///
+/// This covers timeouts, throttling (including e621's `421`), and server side errors (including Cloudflare's `52x`).
+///
+/// # Arguments
+///
+/// * `status`: The status to check.
+///
+/// returns: bool
+pub(crate) fn is_transient_status(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 421 | 429 | 500 | 502..=504 | 520..=524)
+}
+
+/// Checks if an error that happened while sending a request is temporary and worth retrying.
+///
+/// # Arguments
+///
+/// * `error`: The error to check.
+///
+/// returns: bool
+pub(crate) fn is_transient_error(error: &reqwest::Error) -> bool {
+    match error.status() {
+        Some(status) => is_transient_status(status),
+        None => error.is_timeout() || error.is_connect() || error.is_request() || error.is_body(),
+    }
+}
+
+/// Reads how long the server asked the client to wait from the `Retry-After` header, if it gave one in seconds.
+///
+/// The wait is capped to the maximum delay of the policy, so a bad header can't stall the run.
+///
+/// # Arguments
+///
+/// * `policy`: The retry policy to follow.
+/// * `response`: The response to read the header from.
+///
+/// returns: Option<Duration>
+pub(crate) fn retry_after(policy: &RetryPolicy, response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|e| Duration::from_secs(e).min(policy.max_delay()))
+}
+
+/// Calculates how long to wait before the next attempt.
+///
+/// The delay doubles with every attempt until it reaches the maximum delay, and is then randomly shortened by up to
+/// half so that multiple workers failing at the same time don't retry in lockstep.
+///
+/// # Arguments
+///
+/// * `policy`: The retry policy to follow.
+/// * `attempt`: The attempt that just failed, starting at 1.
+///
+/// returns: Duration
+pub(crate) fn backoff_delay(policy: &RetryPolicy, attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(31);
+    let delay = policy
+        .initial_delay()
+        .saturating_mul(1 << exponent)
+        .min(policy.max_delay());
+    delay.mul_f64(0.5 + jitter() * 0.5)
+}
+
+/// Returns a random number between `0.0` and `1.0`.
+fn jitter() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a response with the given `Retry-After` header.
+    fn response_with_retry_after(value: &str) -> Response {
+        Response::from(
+            http::Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .header(RETRY_AFTER, value)
+                .body(String::new())
+                .expect("The response could not be built!"),
+        )
+    }
+
+    #[test]
+    fn transient_statuses_are_retried() {
+        for status in [408, 421, 429, 500, 502, 503, 504, 520, 524] {
+            assert!(is_transient_status(StatusCode::from_u16(status).unwrap()));
+        }
+
+        for status in [200, 400, 401, 403, 404, 416, 501, 525] {
+            assert!(!is_transient_status(StatusCode::from_u16(status).unwrap()));
+        }
+    }
+
+    #[test]
+    fn backoff_delay_doubles_up_to_the_max_delay() {
+        let policy = RetryPolicy::default();
+        for attempt in 1..=10 {
+            let expected = policy
+                .initial_delay()
+                .saturating_mul(1 << (attempt - 1))
+                .min(policy.max_delay());
+            let delay = backoff_delay(&policy, attempt);
+            assert!(delay <= expected, "attempt {attempt} waited {delay:?}");
+            assert!(delay >= expected / 2, "attempt {attempt} waited {delay:?}");
+        }
+    }
+
+    #[test]
+    fn backoff_delay_does_not_overflow_on_late_attempts() {
+        let policy = RetryPolicy::default();
+        assert!(backoff_delay(&policy, u32::MAX) <= policy.max_delay());
+    }
+
+    #[test]
+    fn retry_after_reads_seconds_and_is_capped() {
+        let policy = RetryPolicy::default();
+        assert_eq!(
+            retry_after(&policy, &response_with_retry_after("5")),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            retry_after(&policy, &response_with_retry_after(" 5 ")),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            retry_after(&policy, &response_with_retry_after("86400")),
+            Some(policy.max_delay())
+        );
+    }
+
+    #[test]
+    fn retry_after_ignores_dates_and_invalid_values() {
+        let policy = RetryPolicy::default();
+        assert_eq!(
+            retry_after(
+                &policy,
+                &response_with_retry_after("Wed, 21 Oct 2026 07:28:00 GMT")
+            ),
+            None
+        );
+        assert_eq!(retry_after(&policy, &response_with_retry_after("-1")), None);
+
+        let response = Response::from(
+            http::Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .body(String::new())
+                .expect("The response could not be built!"),
+        );
+        assert_eq!(retry_after(&policy, &response), None);
+    }
+}
@@ -0,0 +1,47 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::future::Future;
+use std::sync::OnceLock;
+
+use tokio::runtime::{Builder, Runtime};
+
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// Gets the runtime every request is sent on, starting it the first time it is used.
+pub(crate) fn runtime() -> &'static Runtime {
+    RUNTIME.get_or_init(|| {
+        trace!("Starting async runtime...");
+        Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("e621-downloader")
+            .build()
+            .expect("Unable to start the async runtime!")
+    })
+}
+
+/// Runs a future on the runtime to completion, blocking the current thread until it is done.
+///
+/// This is the synchronous entry point into the async sender, and must not be called from inside the runtime.
+///
+/// # Arguments
+///
+/// * `future`: The future to run.
+///
+/// returns: `F::Output`
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    runtime().block_on(future)
+}
@@ -0,0 +1,360 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::fs::write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use indicatif::HumanBytes;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Request, Response, Url};
+use serde_json::{Value, json, to_string_pretty};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::e621::error::{E621Error, Result};
+
+/// The query parameters whose values are replaced before a url is recorded.
+const REDACTED_PARAMETERS: [&str; 2] = ["api_key", "password"];
+
+/// A request that was sent during the run.
+struct RequestRecord {
+    /// When the request was first sent.
+    started_at: OffsetDateTime,
+    /// The endpoint the request was sent to (e.g "posts" or "download").
+    endpoint: String,
+    /// The method of the request.
+    method: String,
+    /// The url of the request, with credentials redacted.
+    url: Url,
+    /// The HTTP version of the response, if there was one.
+    http_version: Option<String>,
+    /// The status of the response, if there was one.
+    status: Option<u16>,
+    /// The content type of the response, if there was one.
+    mime_type: Option<String>,
+    /// The size of the response body that was received.
+    bytes: u64,
+    /// How long the request took, including every retry and reading the body.
+    latency: Duration,
+    /// How many times the request was retried.
+    retries: u32,
+    /// Why the request failed, if it did.
+    error: Option<String>,
+}
+
+/// A request that is still being sent or read, which is recorded in the request stats once it finishes.
+///
+/// The request is only recorded once its body is read, so the bytes received and the time spent reading them are part
+/// of the record, and every retry or resume of it is counted towards the same record.
+pub(crate) struct PendingRequest {
+    /// When the request was first sent.
+    started_at: OffsetDateTime,
+    /// When the request was first sent, for measuring how long it took.
+    started: Instant,
+    /// The endpoint the request was sent to.
+    endpoint: String,
+    /// The method of the request.
+    method: String,
+    /// The url of the request, with credentials redacted.
+    url: Url,
+    /// The HTTP version of the last response, if there was one.
+    http_version: Option<String>,
+    /// The status of the last response, if there was one.
+    status: Option<u16>,
+    /// The content type of the last response, if there was one.
+    mime_type: Option<String>,
+    /// How many bytes of the body were received, over every attempt.
+    bytes: u64,
+    /// How many times the request was retried.
+    retries: u32,
+}
+
+impl PendingRequest {
+    /// Starts tracking a request that is about to be sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint`: The endpoint the request is sent to.
+    /// * `request`: The request to send.
+    ///
+    /// returns: `PendingRequest`
+    pub(crate) fn new(endpoint: &str, request: &Request) -> Self {
+        PendingRequest {
+            started_at: OffsetDateTime::now_utc(),
+            started: Instant::now(),
+            endpoint: endpoint.to_string(),
+            method: request.method().to_string(),
+            url: redact_url(request.url()),
+            http_version: None,
+            status: None,
+            mime_type: None,
+            bytes: 0,
+            retries: 0,
+        }
+    }
+
+    /// Counts another attempt at the request.
+    pub(crate) fn retried(&mut self) {
+        self.retries += 1;
+    }
+
+    /// Keeps the details of a response to the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `response`: The response the server sent.
+    pub(crate) fn responded(&mut self, response: &Response) {
+        self.http_version = Some(format!("{:?}", response.version()));
+        self.status = Some(response.status().as_u16());
+        self.mime_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|e| e.to_str().ok())
+            .map(ToString::to_string);
+    }
+
+    /// Counts bytes of the body that were received.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes`: How many bytes were received.
+    pub(crate) fn received(&mut self, bytes: u64) {
+        self.bytes += bytes;
+    }
+}
+
+/// The totals of every request sent to an endpoint.
+#[derive(Default)]
+struct EndpointSummary {
+    /// How many requests were sent.
+    requests: usize,
+    /// How many requests failed.
+    failed: usize,
+    /// How many retries were needed.
+    retries: u32,
+    /// How many bytes were received.
+    bytes: u64,
+    /// How long every request took combined.
+    latency: Duration,
+}
+
+/// Every request sent during the run, which is summarized at the end of it and can be saved as a HAR file.
+///
+/// This is shared between every clone of the sender, so the requests of every download task end up in one place.
+#[derive(Default)]
+pub(crate) struct RequestStats {
+    /// The requests that were sent, in the order they finished.
+    records: Mutex<Vec<RequestRecord>>,
+}
+
+impl RequestStats {
+    /// Records a request once it finished and its body was read, whether it succeeded or not.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: The request that finished.
+    /// * `error`: The error the request failed with, if it did.
+    pub(crate) fn record(&self, request: PendingRequest, error: Option<&E621Error>) {
+        let status = match error {
+            Some(E621Error::HttpStatus { status, .. }) => Some(status.as_u16()),
+            _ => request.status,
+        };
+
+        self.records
+            .lock()
+            .expect("Request stats lock was poisoned!")
+            .push(RequestRecord {
+                started_at: request.started_at,
+                endpoint: request.endpoint,
+                method: request.method,
+                url: request.url,
+                http_version: request.http_version,
+                status,
+                mime_type: request.mime_type,
+                bytes: request.bytes,
+                latency: request.started.elapsed(),
+                retries: request.retries,
+                error: error.map(ToString::to_string),
+            });
+    }
+
+    /// Logs how many requests were sent to each endpoint, how many failed or were retried, how many bytes they
+    /// received, and how long they took on average.
+    pub(crate) fn log_summary(&self) {
+        let records = self
+            .records
+            .lock()
+            .expect("Request stats lock was poisoned!");
+        if records.is_empty() {
+            return;
+        }
+
+        let mut summaries: BTreeMap<&str, EndpointSummary> = BTreeMap::new();
+        for record in records.iter() {
+            let summary = summaries.entry(&record.endpoint).or_default();
+            summary.requests += 1;
+            summary.failed += usize::from(record.error.is_some());
+            summary.retries += record.retries;
+            summary.bytes += record.bytes;
+            summary.latency += record.latency;
+        }
+
+        info!("Request summary:");
+        for (endpoint, summary) in summaries {
+            info!(
+                "  {}: {} requests, {} failed, {} retries, {}, {}ms average",
+                console::style(endpoint).color256(39).italic(),
+                summary.requests,
+                summary.failed,
+                summary.retries,
+                HumanBytes(summary.bytes),
+                (summary.latency / u32::try_from(summary.requests).unwrap_or(u32::MAX)).as_millis()
+            );
+        }
+    }
+
+    /// Saves every request to a file in the HAR format, which can be opened by browser dev tools and HAR viewers.
+    ///
+    /// Only what the requests are recorded with is saved, headers and bodies aren't.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to save the file to.
+    ///
+    /// returns: Result<(), E621Error>
+    pub(crate) fn save_har(&self, path: &Path) -> Result<()> {
+        let records = self
+            .records
+            .lock()
+            .expect("Request stats lock was poisoned!");
+        let entries: Vec<Value> = records.iter().map(har_entry).collect();
+        let har = json!({
+            "log": {
+                "version": "1.2",
+                "creator": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "entries": entries,
+            }
+        });
+
+        let content = to_string_pretty(&har).expect("A json value can always be serialized!");
+        write(path, content).map_err(|e| {
+            error!("Unable to save the request log!");
+            E621Error::filesystem(path, e)
+        })?;
+        info!("Saved the request log to {}...", path.to_string_lossy());
+
+        Ok(())
+    }
+}
+
+/// Converts a recorded request into a HAR entry.
+///
+/// Requests that failed without a response have a status of `0`. Fields that aren't part of the HAR format are
+/// prefixed with `_`.
+///
+/// # Arguments
+///
+/// * `record`: The request to convert.
+///
+/// returns: Value
+fn har_entry(record: &RequestRecord) -> Value {
+    let time = record.latency.as_secs_f64() * 1000.0;
+    let http_version = record.http_version.as_deref().unwrap_or_default();
+    let query: Vec<Value> = record
+        .url
+        .query_pairs()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect();
+
+    json!({
+        "startedDateTime": record.started_at.format(&Rfc3339).unwrap_or_default(),
+        "time": time,
+        "request": {
+            "method": record.method,
+            "url": record.url.as_str(),
+            "httpVersion": http_version,
+            "cookies": [],
+            "headers": [],
+            "queryString": query,
+            "headersSize": -1,
+            "bodySize": 0,
+        },
+        "response": {
+            "status": record.status.unwrap_or_default(),
+            "statusText": "",
+            "httpVersion": http_version,
+            "cookies": [],
+            "headers": [],
+            "content": {
+                "size": record.bytes,
+                "mimeType": record.mime_type.as_deref().unwrap_or_default(),
+            },
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": record.bytes,
+        },
+        "cache": {},
+        "timings": {
+            "send": 0,
+            "wait": time,
+            "receive": 0,
+        },
+        "_endpoint": record.endpoint,
+        "_retries": record.retries,
+        "_error": record.error,
+    })
+}
+
+/// Removes credentials from a url before it is recorded, which are the password of the url and the values of
+/// [`REDACTED_PARAMETERS`] in its query.
+///
+/// # Arguments
+///
+/// * `url`: The url to redact.
+///
+/// returns: Url
+fn redact_url(url: &Url) -> Url {
+    let mut redacted = url.clone();
+    if redacted.password().is_some() {
+        let _ = redacted.set_password(Some("REDACTED"));
+    }
+
+    if url
+        .query_pairs()
+        .any(|(name, _)| REDACTED_PARAMETERS.contains(&name.as_ref()))
+    {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(name, value)| {
+                let value = if REDACTED_PARAMETERS.contains(&name.as_ref()) {
+                    String::from("REDACTED")
+                } else {
+                    value.into_owned()
+                };
+                (name.into_owned(), value)
+            })
+            .collect();
+        redacted.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+
+    redacted
+}
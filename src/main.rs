@@ -21,6 +21,7 @@ use std::env::consts::{
     ARCH, DLL_EXTENSION, DLL_PREFIX, DLL_SUFFIX, EXE_EXTENSION, EXE_SUFFIX, FAMILY, OS,
 };
 use std::fs::File;
+use std::process::ExitCode;
 
 use anyhow::{Context, Error};
 use log::LevelFilter;
@@ -28,17 +29,30 @@ use simplelog::{
     ColorChoice, CombinedLogger, Config, ConfigBuilder, TermLogger, TerminalMode, WriteLogger,
 };
 
+use crate::e621::error::E621Error;
 use crate::program::Program;
 
 mod e621;
 mod program;
 
-fn main() -> Result<(), Error> {
+/// The exit code used when the tag file was just created and needs to be filled in.
+const TAG_FILE_CREATED_EXIT_CODE: u8 = 0xFF;
+
+fn main() -> Result<ExitCode, Error> {
     initialize_logger()?;
     log_system_information();
 
     let program = Program::new();
-    program.run()
+    match program.run() {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(error) => match error.downcast_ref::<E621Error>() {
+            Some(E621Error::TagFileCreated(_)) => {
+                info!("{error}");
+                Ok(ExitCode::from(TAG_FILE_CREATED_EXIT_CODE))
+            }
+            _ => Err(error),
+        },
+    }
 }
 
 /// Initializes the logger with preset filtering.
@@ -16,15 +16,19 @@
 
 use std::env::current_dir;
 use std::fs::write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Error};
+use anyhow::{Context, Error, anyhow};
 use console::Term;
+use dialoguer::Confirm;
 
 use crate::e621::E621WebConnector;
+use crate::e621::error::E621Error;
+use crate::e621::io::arguments::Arguments;
 use crate::e621::io::tag::{TAG_FILE_EXAMPLE, TAG_NAME, parse_tag_file};
-use crate::e621::io::{Config, Login, emergency_exit};
-use crate::e621::sender::RequestSender;
+use crate::e621::io::{Config, InvalidLoginAction, Login};
+use crate::e621::sender::runtime::block_on;
+use crate::e621::sender::{LoginStatus, RequestSender};
 
 /// The name of the cargo package.
 const NAME: &str = env!("CARGO_PKG_NAME");
@@ -46,6 +50,7 @@ impl Program {
 
     /// Runs the downloader program.
     pub(crate) fn run(&self) -> Result<(), Error> {
+        Arguments::initialize()?;
         Term::stdout().set_title("e621 downloader");
         trace!("Starting e621 downloader...");
         trace!("Program Name: {NAME}");
@@ -76,10 +81,7 @@ impl Program {
             write(TAG_NAME, TAG_FILE_EXAMPLE)?;
             trace!("Tag file \"{TAG_NAME}\" created...");
 
-            emergency_exit(
-                "The tag file is created, the application will close so you can include \
-             the artists, sets, pools, and individual posts you wish to download.",
-            );
+            return Err(E621Error::TagFileCreated(PathBuf::from(TAG_NAME)).into());
         }
 
         // Creates connector and requester to prepare for downloading posts.
@@ -94,28 +96,100 @@ impl Program {
             login.ignore_blacklist_on_favorites()
         );
 
-        let request_sender = RequestSender::new();
+        let mut request_sender = RequestSender::new()?;
+        self.verify_login(&mut request_sender)?;
         let mut connector = E621WebConnector::new(&request_sender);
-        connector.should_enter_safe_mode()?;
 
         // Parses tag file.
         trace!("Parsing tag file...");
         let groups = parse_tag_file(&request_sender)?;
 
         // Collects all grabbed posts and moves it to connector to start downloading.
-        if login.is_empty() {
+        if !request_sender.is_authenticated() {
             trace!("Skipping blacklist as user is not logged in...");
         } else {
             trace!("Parsing user blacklist...");
-            connector.process_blacklist();
+            connector.process_blacklist()?;
         }
 
-        connector.grab_all(&groups);
-        connector.download_posts()?;
+        let result = connector.grab_and_download(&groups);
+        request_sender.report_requests();
+        result?;
 
         info!("Finished downloading posts!");
         info!("Exiting...");
 
         Ok(())
     }
+
+    /// Checks the login against the server and shows the result, before anything is grabbed.
+    ///
+    /// If only the API key is missing, the run continues without logging in, since the favorites of the user can still
+    /// be grabbed if they are public. If the login is refused, the user is asked (or the `OnInvalidLogin` option in the
+    /// login file decides) whether to continue anonymously, which makes every request after it anonymous.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_sender`: The sender to check the login with.
+    fn verify_login(&self, request_sender: &mut RequestSender) -> Result<(), Error> {
+        trace!("Verifying login...");
+        let problem = match block_on(request_sender.verify_login())? {
+            LoginStatus::Anonymous => {
+                info!("No login was given, continuing anonymously...");
+                return Ok(());
+            }
+            LoginStatus::Valid(name) => {
+                info!(
+                    "Logged in as {}!",
+                    console::style(format!("\"{name}\"")).color256(39).italic()
+                );
+                return Ok(());
+            }
+            LoginStatus::MissingApiKey => {
+                warn!(
+                    "The `Username` is set in login.json, but the `APIKey` is empty, continuing without logging in. \
+                     Public favorites of the user are still downloaded, but your blacklist will not be used..."
+                );
+                request_sender.set_anonymous();
+                return Ok(());
+            }
+            LoginStatus::MissingUsername => {
+                "The `APIKey` is set in login.json, but the `Username` is empty."
+            }
+            LoginStatus::UnknownUsername => {
+                "No user exists with the `Username` in login.json, check that it is spelled correctly."
+            }
+            LoginStatus::InvalidApiKey => {
+                "The `APIKey` in login.json is wrong for the `Username`, check that it matches the API key in your \
+                 account settings."
+            }
+            LoginStatus::Forbidden => {
+                "The server refused the login in login.json, the account may be banned or the API key may lack access."
+            }
+        };
+
+        error!("{problem}");
+        let continue_anonymously = match Login::get().on_invalid_login() {
+            InvalidLoginAction::Ask => Confirm::new()
+                .with_prompt("Continue anonymously?")
+                .show_default(true)
+                .default(false)
+                .interact()
+                .with_context(|| {
+                    error!("Failed to setup confirmation prompt!");
+                    "Terminal unable to set up confirmation prompt..."
+                })?,
+            InvalidLoginAction::Anonymous => true,
+            InvalidLoginAction::Exit => false,
+        };
+
+        trace!("Continue anonymously decision: {continue_anonymously}");
+        if !continue_anonymously {
+            return Err(anyhow!("Login failed: {problem}"));
+        }
+
+        warn!("Continuing anonymously, your blacklist will not be used...");
+        request_sender.set_anonymous();
+        Ok(())
+    }
 }